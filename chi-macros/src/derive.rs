@@ -0,0 +1,137 @@
+use proc_macro2::{Ident, Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::{parse_quote, Data, DeriveInput, Fields, GenericParam, Generics, Path};
+
+/// A struct or a variant of an enum: the constructor it is written as, the
+/// path to build it with, and its fields.
+struct Shape<'a> {
+    constructor: String,
+    path: Path,
+    fields: &'a Fields,
+}
+
+impl Shape<'_> {
+    fn bindings(&self) -> Vec<Ident> {
+        (0..self.fields.len())
+            .map(|i| format_ident!("field_{}", i))
+            .collect()
+    }
+
+    /// `path`, applied to `values` in the way its fields are declared, as a
+    /// pattern or an expression.
+    fn build(&self, values: &[TokenStream2]) -> TokenStream2 {
+        let path = &self.path;
+        match self.fields {
+            Fields::Named(fields) => {
+                let names = fields.named.iter().map(|field| &field.ident);
+                quote!(#path { #(#names: #values),* })
+            }
+            Fields::Unnamed(_) => quote!(#path(#(#values),*)),
+            Fields::Unit => quote!(#path),
+        }
+    }
+
+    fn encode(&self) -> TokenStream2 {
+        let bindings = self.bindings();
+        let pattern = self.build(&bindings.iter().map(|x| quote!(#x)).collect::<Vec<_>>());
+        let constructor = &self.constructor;
+        quote! {
+            #pattern => ::chi::syntax::Exp::Const(
+                ::std::string::ToString::to_string(#constructor),
+                ::std::vec![#(::chi::convert::ToChi::to_chi(#bindings)),*],
+            ),
+        }
+    }
+
+    fn decode(&self) -> TokenStream2 {
+        let constructor = &self.constructor;
+        let arity = self.fields.len();
+        let values: Vec<_> = (0..arity)
+            .map(|i| quote!(::chi::convert::FromChi::from_chi(&arguments[#i])?))
+            .collect();
+        let value = self.build(&values);
+        quote! {
+            if let ::core::option::Option::Some(arguments) =
+                ::chi::convert::arguments(exp, #constructor, #arity)?
+            {
+                return ::core::result::Result::Ok(#value);
+            }
+        }
+    }
+}
+
+fn shapes(input: &DeriveInput) -> syn::Result<Vec<Shape<'_>>> {
+    match &input.data {
+        Data::Struct(data) => Ok(vec![Shape {
+            constructor: input.ident.to_string(),
+            path: parse_quote!(Self),
+            fields: &data.fields,
+        }]),
+        Data::Enum(data) => Ok(data
+            .variants
+            .iter()
+            .map(|variant| {
+                let name = &variant.ident;
+                Shape {
+                    constructor: name.to_string(),
+                    path: parse_quote!(Self::#name),
+                    fields: &variant.fields,
+                }
+            })
+            .collect()),
+        Data::Union(_) => Err(syn::Error::new(
+            Span::call_site(),
+            "unions cannot be converted to χ values",
+        )),
+    }
+}
+
+/// `generics` with `bound` on every type parameter.
+fn bounded(generics: &Generics, bound: Path) -> Generics {
+    let mut generics = generics.clone();
+    for parameter in &mut generics.params {
+        if let GenericParam::Type(parameter) = parameter {
+            parameter.bounds.push(parse_quote!(#bound));
+        }
+    }
+    generics
+}
+
+pub fn expand_to_chi(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let arms = shapes(&input)?
+        .iter()
+        .map(Shape::encode)
+        .collect::<Vec<_>>();
+    let name = &input.ident;
+    let generics = bounded(&input.generics, parse_quote!(::chi::convert::ToChi));
+    let (impl_generics, type_generics, where_clause) = generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::chi::convert::ToChi for #name #type_generics #where_clause {
+            fn to_chi(&self) -> ::chi::syntax::Exp {
+                match self {
+                    #(#arms)*
+                }
+            }
+        }
+    })
+}
+
+pub fn expand_from_chi(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let attempts = shapes(&input)?
+        .iter()
+        .map(Shape::decode)
+        .collect::<Vec<_>>();
+    let name = &input.ident;
+    let generics = bounded(&input.generics, parse_quote!(::chi::convert::FromChi));
+    let (impl_generics, type_generics, where_clause) = generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::chi::convert::FromChi for #name #type_generics #where_clause {
+            fn from_chi(
+                exp: &::chi::syntax::Exp,
+            ) -> ::core::result::Result<Self, ::chi::convert::FromChiError> {
+                #(#attempts)*
+                ::core::result::Result::Err(::chi::convert::FromChiError::unexpected::<Self>(exp))
+            }
+        }
+    })
+}
@@ -0,0 +1,51 @@
+//! Macros for using χ from Rust. The code they expand to refers to the `chi`
+//! crate, which has to be a dependency too.
+
+use proc_macro::TokenStream;
+use syn::{parse_macro_input, LitStr};
+
+mod derive;
+mod program;
+
+/// Implements `chi::convert::ToChi` by writing a struct, or each variant of an
+/// enum, as a constructor of the same name applied to its fields in the order
+/// they are declared.
+#[proc_macro_derive(ToChi)]
+pub fn derive_to_chi(input: TokenStream) -> TokenStream {
+    derive::expand_to_chi(parse_macro_input!(input))
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Implements `chi::convert::FromChi` as the inverse of `#[derive(ToChi)]`,
+/// failing on a constructor of another name or with another number of
+/// arguments.
+#[proc_macro_derive(FromChi)]
+pub fn derive_from_chi(input: TokenStream) -> TokenStream {
+    derive::expand_from_chi(parse_macro_input!(input))
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// A χ expression in concrete syntax, checked when compiling and built as an
+/// `chi::syntax::Exp`. `#x` or `#(…)` in place of a subterm splices in the
+/// value of a Rust expression implementing `chi::convert::ToChi`.
+///
+/// Rust has to be able to split the code into tokens, so write `->` for `→`
+/// and `λ` rather than a backslash.
+#[proc_macro]
+pub fn chi(input: TokenStream) -> TokenStream {
+    program::expand_chi(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// A file of χ code, in concrete syntax, checked when compiling and built as
+/// an `chi::syntax::Exp`. The path is relative to the file the macro is used
+/// in, as for `include_str!`.
+#[proc_macro]
+pub fn include_chi(input: TokenStream) -> TokenStream {
+    program::expand_include_chi(parse_macro_input!(input as LitStr))
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
@@ -0,0 +1,230 @@
+use std::{fs, ops::Range, path::PathBuf};
+
+use chi_syntax::{
+    concrete::{self, SyntaxError},
+    Exp,
+};
+use proc_macro::{Delimiter, Span, TokenStream, TokenTree};
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::LitStr;
+
+const SPLICE: &str = "__chi_splice_";
+
+/// The code of a `chi!` invocation, laid out as it is written, with where each
+/// token is in it and the spliced Rust expressions.
+#[derive(Default)]
+struct Source {
+    code: String,
+    tokens: Vec<(Range<usize>, Span)>,
+    splices: Vec<(TokenStream2, proc_macro2::Span)>,
+    /// The line and column the last token ends at.
+    end: Option<(usize, usize)>,
+}
+
+impl Source {
+    fn push(&mut self, text: &str, span: Span) {
+        let (line, column) = (span.start().line(), span.start().column());
+        match self.end {
+            Some((end_line, _)) if line > end_line => self.code.push('\n'),
+            Some((_, end_column)) => {
+                let gap = column.saturating_sub(end_column);
+                self.code.extend(std::iter::repeat_n(' ', gap));
+            }
+            None => {}
+        }
+        let start = self.code.len();
+        self.code.push_str(text);
+        self.tokens.push((start..self.code.len(), span));
+        self.end = Some((span.end().line(), span.end().column()));
+    }
+
+    fn splice(&mut self, expression: TokenStream2, span: Span) {
+        let placeholder = format!("{}{}", SPLICE, self.splices.len());
+        self.push(&placeholder, span);
+        self.splices.push((expression, span.into()));
+    }
+
+    fn read(&mut self, input: TokenStream) {
+        let mut trees = input.into_iter().peekable();
+        while let Some(tree) = trees.next() {
+            match tree {
+                TokenTree::Punct(punct) if punct.as_char() == '#' => match trees.peek() {
+                    Some(TokenTree::Ident(ident)) => {
+                        let span = ident.span();
+                        let ident = TokenStream2::from(TokenStream::from(trees.next().unwrap()));
+                        self.splice(ident, span);
+                    }
+                    Some(TokenTree::Group(group))
+                        if group.delimiter() == Delimiter::Parenthesis =>
+                    {
+                        let span = group.span();
+                        let expression = TokenStream2::from(group.stream());
+                        trees.next();
+                        self.splice(expression, span);
+                    }
+                    _ => self.push("#", punct.span()),
+                },
+                TokenTree::Group(group) => {
+                    let (open, close) = match group.delimiter() {
+                        Delimiter::Parenthesis => ("(", ")"),
+                        Delimiter::Brace => ("{", "}"),
+                        Delimiter::Bracket => ("[", "]"),
+                        Delimiter::None => ("", ""),
+                    };
+                    self.push(open, group.span_open());
+                    self.read(group.stream());
+                    self.push(close, group.span_close());
+                }
+                tree => self.push(&tree.to_string(), tree.span()),
+            }
+        }
+    }
+
+    /// The span of the token at or after `offset`, for reporting an error
+    /// there.
+    fn span_at(&self, offset: usize) -> proc_macro2::Span {
+        self.tokens
+            .iter()
+            .find(|(range, _)| offset < range.end)
+            .or_else(|| self.tokens.last())
+            .map_or_else(proc_macro2::Span::call_site, |(_, span)| (*span).into())
+    }
+}
+
+/// The code of a `String` with `text`. The code built names everything by its
+/// full path, as the prelude may be shadowed where the macro is used.
+fn string(text: &str) -> TokenStream2 {
+    quote!(::std::string::ToString::to_string(#text))
+}
+
+fn boxed(exp: TokenStream2) -> TokenStream2 {
+    quote!(::std::boxed::Box::new(#exp))
+}
+
+/// Builds `exp`, with the variables standing for splices replaced by them.
+struct Builder<'a> {
+    splices: &'a [(TokenStream2, proc_macro2::Span)],
+}
+
+impl Builder<'_> {
+    fn splice(&self, name: &str) -> Option<&(TokenStream2, proc_macro2::Span)> {
+        self.splices
+            .get(name.strip_prefix(SPLICE)?.parse::<usize>().ok()?)
+    }
+
+    fn binder(&self, name: &str) -> syn::Result<TokenStream2> {
+        if let Some((_, span)) = self.splice(name) {
+            return Err(syn::Error::new(
+                *span,
+                "only subterms can be spliced, not the names of binders",
+            ));
+        }
+        Ok(string(name))
+    }
+
+    fn build(&self, exp: &Exp) -> syn::Result<TokenStream2> {
+        Ok(match exp {
+            Exp::Var(x) => match self.splice(x) {
+                Some((splice, _)) => quote!(::chi::convert::ToChi::to_chi(&(#splice))),
+                None => {
+                    let x = string(x);
+                    quote!(::chi::syntax::Exp::Var(#x))
+                }
+            },
+            Exp::Apply(f, x) => {
+                let (f, x) = (boxed(self.build(f)?), boxed(self.build(x)?));
+                quote!(::chi::syntax::Exp::Apply(#f, #x))
+            }
+            Exp::Lambda(x, body) => {
+                let (x, body) = (self.binder(x)?, boxed(self.build(body)?));
+                quote!(::chi::syntax::Exp::Lambda(#x, #body))
+            }
+            Exp::Rec(x, body) => {
+                let (x, body) = (self.binder(x)?, boxed(self.build(body)?));
+                quote!(::chi::syntax::Exp::Rec(#x, #body))
+            }
+            Exp::Case(e, branches) => {
+                let e = boxed(self.build(e)?);
+                let branches = branches
+                    .iter()
+                    .map(|branch| {
+                        let constructor = string(&branch.constructor);
+                        let parameters = branch
+                            .parameters
+                            .iter()
+                            .map(|x| self.binder(x))
+                            .collect::<syn::Result<Vec<_>>>()?;
+                        let expression = boxed(self.build(&branch.expression)?);
+                        Ok(quote! {
+                            ::chi::syntax::Branch {
+                                constructor: #constructor,
+                                parameters: ::std::vec![#(#parameters),*],
+                                expression: #expression,
+                            }
+                        })
+                    })
+                    .collect::<syn::Result<Vec<_>>>()?;
+                quote!(::chi::syntax::Exp::Case(#e, ::std::vec![#(#branches),*]))
+            }
+            Exp::Const(constructor, arguments) => {
+                let constructor = string(constructor);
+                let arguments = arguments
+                    .iter()
+                    .map(|argument| self.build(argument))
+                    .collect::<syn::Result<Vec<_>>>()?;
+                quote!(::chi::syntax::Exp::Const(#constructor, ::std::vec![#(#arguments),*]))
+            }
+        })
+    }
+}
+
+pub fn expand_chi(input: TokenStream) -> syn::Result<TokenStream2> {
+    let mut source = Source::default();
+    source.read(input);
+    let exp = match concrete::parse_spanned(&source.code) {
+        Ok((exp, _)) => exp,
+        Err(SyntaxError { offset }) => {
+            let message = match source.code[offset..].chars().next() {
+                Some(_) => "invalid χ syntax here",
+                None => "unexpected end of χ code",
+            };
+            return Err(syn::Error::new(source.span_at(offset), message));
+        }
+    };
+    Builder {
+        splices: &source.splices,
+    }
+    .build(&exp)
+}
+
+pub fn expand_include_chi(path: LitStr) -> syn::Result<TokenStream2> {
+    let directory = Span::call_site()
+        .local_file()
+        .and_then(|file| file.parent().map(PathBuf::from))
+        .unwrap_or_else(|| PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap()));
+    let file = directory.join(path.value());
+    let code = fs::read_to_string(&file).map_err(|error| {
+        syn::Error::new(
+            path.span(),
+            format!("cannot read {}: {}", file.display(), error),
+        )
+    })?;
+    let exp = concrete::parse_spanned(&code).map_err(|SyntaxError { offset }| {
+        let line = code[..offset].matches('\n').count() + 1;
+        let column = code[..offset].rsplit('\n').next().unwrap().chars().count() + 1;
+        syn::Error::new(
+            path.span(),
+            format!("invalid χ syntax at {}:{}:{}", file.display(), line, column),
+        )
+    })?;
+    let exp = Builder { splices: &[] }.build(&exp.0)?;
+    // Makes the crate be rebuilt when the file changes. The path is relative
+    // to where the compiler runs, not to the file using the macro.
+    let file = fs::canonicalize(&file).unwrap_or(file);
+    let file = file.to_string_lossy();
+    Ok(quote!({
+        const _: &str = ::core::include_str!(#file);
+        #exp
+    }))
+}
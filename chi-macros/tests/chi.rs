@@ -0,0 +1,74 @@
+use chi::{
+    semantic::eval,
+    syntax::{concrete, Exp},
+};
+use chi_macros::{chi, include_chi};
+
+fn parse(code: &str) -> Exp {
+    concrete::parse(code).unwrap()
+}
+
+#[test]
+fn test_chi() {
+    assert_eq!(chi! { λx. Suc(x) }, parse("λx. Suc(x)"));
+    assert_eq!(
+        chi! {
+            rec add = λm. λn. case n of {
+                Zero() -> m;
+                Suc(n) -> Suc(add m n)
+            }
+        },
+        parse("rec add = λm. λn. case n of { Zero() → m; Suc(n) → Suc(add m n) }")
+    );
+    assert_eq!(chi!(add-one (f x) C()), parse("add-one (f x) C()"));
+
+    let add = chi! {
+        rec add = λm. λn. case n of { Zero() -> m; Suc(n) -> Suc(add m n) }
+    };
+    let two = 2usize;
+    assert_eq!(
+        eval(&chi!(#add #two #(1 + 1))),
+        eval(&parse("Suc(Suc(Suc(Suc(Zero()))))"))
+    );
+    assert_eq!(
+        chi!(Pair(#two, #(vec![true]))),
+        parse("Pair(Suc(Suc(Zero())), Cons(True(), Nil()))")
+    );
+}
+
+#[test]
+fn test_include_chi() {
+    assert_eq!(
+        include_chi!("../../src/bootstrapping/eval.chi"),
+        parse(include_str!("../../src/bootstrapping/eval.chi"))
+    );
+}
+
+/// The code built names everything by its full path, as the names of the
+/// prelude may mean something else where the macros are used.
+mod shadowed {
+    #![allow(dead_code, unused_macros)]
+
+    use chi::syntax::Exp;
+    use chi_macros::chi;
+
+    struct Box;
+    trait ToString {}
+    macro_rules! vec {
+        ($($t:tt)*) => {
+            compile_error!("the prelude's vec! is shadowed")
+        };
+    }
+
+    pub fn swap() -> Exp {
+        chi! { λp. case p of { Pair(x, y) -> Pair(y, x) } }
+    }
+}
+
+#[test]
+fn test_shadowed_prelude() {
+    assert_eq!(
+        shadowed::swap(),
+        parse("λp. case p of { Pair(x, y) → Pair(y, x) }")
+    );
+}
@@ -0,0 +1,98 @@
+use chi::{
+    convert::{FromChi, FromChiError, ToChi},
+    semantic::eval,
+    syntax::{concrete, Exp},
+};
+use chi_macros::{FromChi, ToChi};
+
+#[derive(Debug, PartialEq, ToChi, FromChi)]
+enum Tree<T> {
+    Leaf,
+    Node(Box<Tree<T>>, T, Box<Tree<T>>),
+}
+
+#[derive(Debug, PartialEq, ToChi, FromChi)]
+struct Labelled {
+    label: String,
+    count: usize,
+    tree: Tree<bool>,
+}
+
+/// The generated code has to name everything with full paths, as the names of
+/// the prelude may mean something else where a type derives the traits.
+mod shadowed {
+    #![allow(dead_code, unused_macros)]
+
+    use chi_macros::{FromChi, ToChi};
+
+    type Result = ();
+    struct Ok;
+    struct Err;
+    struct Some;
+    trait ToString {}
+    macro_rules! vec {
+        ($($t:tt)*) => {
+            compile_error!("the prelude's vec! is shadowed")
+        };
+    }
+
+    #[derive(Debug, PartialEq, ToChi, FromChi)]
+    pub enum Pair {
+        Pair(bool, bool),
+    }
+}
+
+fn parse(code: &str) -> Exp {
+    concrete::parse(code).unwrap()
+}
+
+#[test]
+fn test_derive() {
+    let tree = Tree::Node(Box::new(Tree::Leaf), 1usize, Box::new(Tree::Leaf));
+    assert_eq!(
+        concrete::format(&tree.to_chi()),
+        "Node(Leaf(), Suc(Zero()), Leaf())"
+    );
+    let labelled = Labelled {
+        label: "a".to_string(),
+        count: 0,
+        tree: Tree::Leaf,
+    };
+    assert_eq!(Labelled::from_chi(&labelled.to_chi()), Ok(labelled));
+
+    let mirror = parse(
+        "rec mirror = λt. case t of {
+            Leaf() → Leaf();
+            Node(l, x, r) → Node(mirror r, x, mirror l)
+        }",
+    );
+    let tree = Tree::Node(
+        Box::new(Tree::Node(Box::new(Tree::Leaf), true, Box::new(Tree::Leaf))),
+        false,
+        Box::new(Tree::Leaf),
+    );
+    let mirrored = eval(&Exp::Apply(Box::new(mirror), Box::new(tree.to_chi())));
+    assert_eq!(
+        Tree::from_chi(&mirrored),
+        Ok(Tree::Node(
+            Box::new(Tree::Leaf),
+            false,
+            Box::new(Tree::Node(Box::new(Tree::Leaf), true, Box::new(Tree::Leaf)))
+        ))
+    );
+
+    assert_eq!(
+        Tree::<bool>::from_chi(&parse("Node(Leaf(), True())")),
+        Err(FromChiError::Arity {
+            constructor: "Node".to_string(),
+            expected: 3,
+            found: 2
+        })
+    );
+    let error = Tree::<bool>::from_chi(&parse("Branch()")).unwrap_err();
+    assert!(matches!(error, FromChiError::UnexpectedTerm { .. }));
+    assert!(error.to_string().contains("Tree<bool>"));
+
+    let pair = shadowed::Pair::Pair(true, false);
+    assert_eq!(shadowed::Pair::from_chi(&pair.to_chi()), Ok(pair));
+}
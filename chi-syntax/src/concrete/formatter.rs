@@ -1,4 +1,4 @@
-use crate::syntax::{Branch, Exp};
+use crate::{Branch, Exp};
 
 fn format_branch(branch: &Branch, nest_level: usize) -> String {
     format!(
@@ -64,7 +64,7 @@ pub fn format(exp: &Exp) -> String {
 
 #[cfg(test)]
 mod tests {
-    use crate::syntax::concrete::parser::parse;
+    use crate::concrete::parser::parse;
 
     use super::*;
 
@@ -0,0 +1,5 @@
+mod formatter;
+mod parser;
+
+pub use formatter::format;
+pub use parser::{parse, parse_spanned, Span, SpanKind, SyntaxError};
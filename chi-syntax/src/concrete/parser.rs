@@ -1,3 +1,5 @@
+use std::{cell::RefCell, ops::Range};
+
 use nom::{
     branch::alt,
     bytes::complete::tag,
@@ -9,7 +11,83 @@ use nom::{
     IResult,
 };
 
-use crate::syntax::{constructor, variable, Branch, Exp};
+use crate::{constructor, variable, Branch, Exp};
+
+/// What the source range of a [`Span`] was parsed as.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SpanKind {
+    Expression,
+    /// A case branch, from its constructor to the end of its expression.
+    Branch,
+}
+
+/// The byte range of `code` a subterm was parsed from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Span {
+    pub kind: SpanKind,
+    pub range: Range<usize>,
+}
+
+/// The position at which `parse_spanned` gave up.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SyntaxError {
+    /// The byte offset of the furthest point any subterm could be parsed up
+    /// to, where the error most likely is.
+    pub offset: usize,
+}
+
+/// Spans of subterms parsed so far, as addresses into the code, and the
+/// furthest address reached. Only recorded during `parse_spanned`.
+#[derive(Default)]
+struct Recorder {
+    spans: Vec<(SpanKind, usize, usize)>,
+    furthest: usize,
+}
+
+thread_local! {
+    static RECORDER: RefCell<Option<Recorder>> = const { RefCell::new(None) };
+}
+
+fn address(code: &str) -> usize {
+    code.as_ptr() as usize
+}
+
+/// Records the span of whatever `parser` parses. Spans recorded inside a
+/// failing parser are dropped again.
+fn spanned<'a, O>(
+    kind: SpanKind,
+    mut parser: impl FnMut(&'a str) -> IResult<&'a str, O>,
+) -> impl FnMut(&'a str) -> IResult<&'a str, O> {
+    move |input: &'a str| {
+        let recorded = RECORDER.with(|recorder| {
+            recorder
+                .borrow()
+                .as_ref()
+                .map(|recorder| recorder.spans.len())
+        });
+        let Some(recorded) = recorded else {
+            return parser(input);
+        };
+        let result = parser(input);
+        RECORDER.with(|recorder| {
+            let mut recorder = recorder.borrow_mut();
+            let recorder = recorder.as_mut().unwrap();
+            match &result {
+                Ok((rest, _)) => {
+                    let start = address(input.trim_start());
+                    recorder.spans.push((kind, start, address(rest)));
+                    recorder.furthest = recorder.furthest.max(address(rest));
+                }
+                Err(nom::Err::Error(error) | nom::Err::Failure(error)) => {
+                    recorder.spans.truncate(recorded);
+                    recorder.furthest = recorder.furthest.max(address(error.input));
+                }
+                Err(nom::Err::Incomplete(_)) => recorder.spans.truncate(recorded),
+            }
+        });
+        result
+    }
+}
 
 fn parse_const(code: &str) -> IResult<&str, Exp> {
     map(
@@ -65,25 +143,28 @@ fn case_exp(code: &str) -> IResult<&str, Exp> {
 }
 
 fn branch(input: &str) -> IResult<&str, Branch> {
-    map(
-        tuple((
-            preceded(multispace0, constructor),
-            delimited(
-                preceded(multispace0, char('(')),
-                separated_list0(
-                    preceded(multispace0, char(',')),
-                    preceded(multispace0, variable),
+    spanned(
+        SpanKind::Branch,
+        map(
+            tuple((
+                preceded(multispace0, constructor),
+                delimited(
+                    preceded(multispace0, char('(')),
+                    separated_list0(
+                        preceded(multispace0, char(',')),
+                        preceded(multispace0, variable),
+                    ),
+                    preceded(multispace0, char(')')),
                 ),
-                preceded(multispace0, char(')')),
-            ),
-            preceded(multispace0, alt((tag("->"), tag("→")))),
-            preceded(multispace0, parse_exp),
-        )),
-        |(constructor, parameters, _, expression)| Branch {
-            constructor,
-            parameters,
-            expression: Box::new(expression),
-        },
+                preceded(multispace0, alt((tag("->"), tag("→")))),
+                preceded(multispace0, parse_exp),
+            )),
+            |(constructor, parameters, _, expression)| Branch {
+                constructor,
+                parameters,
+                expression: Box::new(expression),
+            },
+        ),
     )(input)
 }
 
@@ -103,16 +184,19 @@ fn case(code: &str) -> IResult<&str, Exp> {
 }
 
 fn higher_than_apply(code: &str) -> IResult<&str, Exp> {
-    alt((
-        map(variable, Exp::Var),
-        parse_const,
-        delimited(
-            preceded(multispace0, char('(')),
-            parse_exp,
-            preceded(multispace0, char(')')),
-        ),
-        case,
-    ))(code)
+    spanned(
+        SpanKind::Expression,
+        alt((
+            map(variable, Exp::Var),
+            parse_const,
+            delimited(
+                preceded(multispace0, char('(')),
+                parse_exp,
+                preceded(multispace0, char(')')),
+            ),
+            case,
+        )),
+    )(code)
 }
 
 fn apply(code: &str) -> IResult<&str, Exp> {
@@ -182,25 +266,92 @@ pub fn remove_comment(code: &str) -> String {
     result
 }
 
+/// Like `remove_comment`, but replaces comments with spaces, so offsets into
+/// the result are offsets into `code`.
+fn blank_comment(code: &str) -> String {
+    let mut in_multiline_comment = false;
+    let mut in_inline_comment = false;
+    let mut result = String::with_capacity(code.len());
+    let mut chars = code.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let in_comment = in_inline_comment || in_multiline_comment;
+        match c {
+            '\n' => {
+                in_inline_comment = false;
+                result.push(c);
+            }
+            '-' if !in_comment && chars.peek() == Some(&'-') => {
+                in_inline_comment = true;
+                result.push(' ');
+            }
+            '{' if !in_inline_comment && chars.peek() == Some(&'-') => {
+                chars.next();
+                in_multiline_comment = true;
+                result.push_str("  ");
+            }
+            '-' if in_multiline_comment && chars.peek() == Some(&'}') => {
+                chars.next();
+                in_multiline_comment = false;
+                result.push_str("  ");
+            }
+            c if in_comment => {
+                result.extend(std::iter::repeat_n(' ', c.len_utf8()));
+            }
+            c => result.push(c),
+        }
+    }
+    result
+}
+
 fn parse_exp(input: &str) -> IResult<&str, Exp> {
-    alt((lambda, rec, case, apply, higher_than_apply))(input)
+    spanned(
+        SpanKind::Expression,
+        alt((lambda, rec, case, apply, higher_than_apply)),
+    )(input)
 }
 
 pub fn parse(input: &str) -> Result<Exp, ()> {
     let code = remove_comment(input);
-    if let Ok((rest, exp)) = parse_exp(&code)
-        && rest.trim() == ""
-    {
-        Ok(exp)
-    } else {
-        Err(())
+    match parse_exp(&code) {
+        Ok((rest, exp)) if rest.trim() == "" => Ok(exp),
+        _ => Err(()),
+    }
+}
+
+/// Like [`parse`], but also returns the spans of the subterms, sorted by
+/// their start and with outer spans first, or where parsing failed.
+pub fn parse_spanned(input: &str) -> Result<(Exp, Vec<Span>), SyntaxError> {
+    let code = blank_comment(input);
+    let base = address(&code);
+    RECORDER.with(|recorder| *recorder.borrow_mut() = Some(Recorder::default()));
+    let result = parse_exp(&code);
+    let recorder = RECORDER.with(|recorder| recorder.borrow_mut().take().unwrap());
+    match result {
+        Ok((rest, exp)) if rest.trim().is_empty() => {
+            let mut spans: Vec<_> = recorder
+                .spans
+                .into_iter()
+                .map(|(kind, start, end)| Span {
+                    kind,
+                    range: start - base..end - base,
+                })
+                .collect();
+            spans.sort_by_key(|span| (span.range.start, usize::MAX - span.range.end, span.kind));
+            spans.dedup();
+            Ok((exp, spans))
+        }
+        Ok((rest, _)) => Err(SyntaxError {
+            offset: address(rest.trim_start()) - base,
+        }),
+        Err(_) => Err(SyntaxError {
+            offset: recorder.furthest.saturating_sub(base).min(code.len()),
+        }),
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::assert_matches::assert_matches;
-
     use super::*;
 
     #[test]
@@ -390,12 +541,29 @@ mod tests {
               }
 }) Suc(Suc(Zero())) Suc(Zero())"#;
         let parsed = parse(code).unwrap();
-        assert_matches!(
-            parsed,
-            Exp::Apply(
-                box Exp::Apply(box Exp::Rec(_, _), box Exp::Const(_, _)),
-                box Exp::Const(_, _),
-            )
-        );
+        assert!(matches!(
+            &parsed,
+            Exp::Apply(f, x) if matches!(
+                &**f,
+                Exp::Apply(g, y) if matches!(**g, Exp::Rec(_, _)) && matches!(**y, Exp::Const(_, _))
+            ) && matches!(**x, Exp::Const(_, _))
+        ));
+    }
+
+    #[test]
+    fn test_parse_spanned() {
+        let code = "λx. f x -- apply\n{- C -} (case x of { C(y) → y })";
+        let (exp, spans) = parse_spanned(code).unwrap();
+        assert_eq!(exp, parse(code).unwrap());
+        let text = |span: &Span| &code[span.range.clone()];
+        assert_eq!(text(&spans[0]), code);
+        assert!(spans.iter().any(|span| text(span) == "f"));
+        let case = spans.iter().find(|span| text(span).starts_with("case"));
+        assert_eq!(text(case.unwrap()), "case x of { C(y) → y }");
+        let branch = spans.iter().find(|span| span.kind == SpanKind::Branch);
+        assert_eq!(text(branch.unwrap()), "C(y) → y");
+
+        assert_eq!(parse_spanned("C(x,"), Err(SyntaxError { offset: 4 }));
+        assert_eq!(parse_spanned("x ) y"), Err(SyntaxError { offset: 2 }));
     }
 }
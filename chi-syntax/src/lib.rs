@@ -0,0 +1,127 @@
+//! The expressions of χ and their concrete syntax, apart from the rest of
+//! `chi` so that `chi-macros` can parse χ code for the `chi` crate itself.
+
+use nom::{
+    branch::alt,
+    character::complete::{alphanumeric1, one_of},
+    combinator::{map, recognize},
+    multi::many0,
+    sequence::pair,
+    IResult,
+};
+use serde::{Deserialize, Serialize};
+
+pub mod concrete;
+mod path;
+
+pub type Variable = String;
+pub type Constructor = String;
+/// A step from an expression to one of its immediate subterms. The steps from
+/// the same expression are ordered as the subterms are written.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Hash)]
+pub enum Step {
+    /// The function of an application.
+    ApplyFun,
+    /// The argument of an application.
+    ApplyArg,
+    /// The body of a λ.
+    LambdaBody,
+    /// The body of a `rec`.
+    RecBody,
+    /// The expression a case expression inspects.
+    Scrutinee,
+    /// The given branch of a case expression, which is not an expression
+    /// itself: a path goes on from it with [`Step::BranchBody`].
+    Branch(usize),
+    /// The expression of a branch.
+    BranchBody,
+    /// The given argument of a constructor application.
+    Argument(usize),
+}
+
+/// The position of a subterm, as the steps taken to it from the root, or of a
+/// branch, if it ends with [`Step::Branch`].
+pub type Path = Vec<Step>;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Hash)]
+pub struct Branch {
+    pub constructor: Constructor,
+    pub parameters: Vec<Variable>,
+    pub expression: Box<Exp>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Exp {
+    Apply(Box<Exp>, Box<Exp>),
+    Lambda(String, Box<Exp>),
+    Case(Box<Exp>, Vec<Branch>),
+    Rec(Variable, Box<Exp>),
+    Var(Variable),
+    Const(String, Vec<Exp>),
+}
+
+/// Parses the name of a constructor, for the parsers of every syntax.
+pub fn constructor(input: &str) -> IResult<&str, Constructor> {
+    let first = one_of("ABCDEFGHIJKLMNOPQRSTUVWXYZ");
+    let rest = recognize(many0(alt((alphanumeric1, recognize(one_of("_-'"))))));
+
+    map(pair(first, rest), |(f, r): (char, &str)| {
+        format!("{}{}", f, r)
+    })(input)
+}
+
+/// Parses the name of a variable, for the parsers of every syntax.
+pub fn variable(input: &str) -> IResult<&str, Variable> {
+    let first = one_of("abcdefghijklmnopqrstuvwxyz_");
+    let rest = recognize(many0(alt((alphanumeric1, recognize(one_of("_-'"))))));
+
+    map(pair(first, rest), |(f, r): (char, &str)| {
+        format!("{}{}", f, r)
+    })(input)
+}
+
+/// Whether `name` can be written as a variable in concrete syntax.
+pub fn is_variable_name(name: &str) -> bool {
+    !["case", "of", "rec"].contains(&name) && matches!(variable(name), Ok(("", _)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_constructor() {
+        let code = "Foo";
+        let (_, constr) = constructor(code).unwrap();
+        assert_eq!(constr, "Foo");
+
+        let code = "Foo_bar";
+        let (_, constr) = constructor(code).unwrap();
+        assert_eq!(constr, "Foo_bar");
+
+        let code = "Foo'bar";
+        let (_, constr) = constructor(code).unwrap();
+        assert_eq!(constr, "Foo'bar");
+
+        let code = "foo";
+        assert!(constructor(code).is_err());
+    }
+
+    #[test]
+    fn test_parse_variable() {
+        let code = "foo";
+        let (_, var) = variable(code).unwrap();
+        assert_eq!(var, "foo");
+
+        let code = "foo_bar";
+        let (_, var) = variable(code).unwrap();
+        assert_eq!(var, "foo_bar");
+
+        let code = "foo'bar";
+        let (_, var) = variable(code).unwrap();
+        assert_eq!(var, "foo'bar");
+
+        let code = "Foo";
+        assert!(variable(code).is_err());
+    }
+}
@@ -0,0 +1,218 @@
+use crate::{Branch, Exp, Path, Step};
+
+impl Exp {
+    /// The immediate subterms of `self`, with the steps to them.
+    fn children(&self) -> Vec<(Path, &Exp)> {
+        match self {
+            Exp::Var(_) => vec![],
+            Exp::Apply(f, x) => vec![(vec![Step::ApplyFun], f), (vec![Step::ApplyArg], x)],
+            Exp::Lambda(_, body) => vec![(vec![Step::LambdaBody], body)],
+            Exp::Rec(_, body) => vec![(vec![Step::RecBody], body)],
+            Exp::Case(e, branches) => std::iter::once((vec![Step::Scrutinee], &**e))
+                .chain(branches.iter().enumerate().map(|(i, branch)| {
+                    (vec![Step::Branch(i), Step::BranchBody], &*branch.expression)
+                }))
+                .collect(),
+            Exp::Const(_, arguments) => arguments
+                .iter()
+                .enumerate()
+                .map(|(i, argument)| (vec![Step::Argument(i)], argument))
+                .collect(),
+        }
+    }
+
+    /// The immediate subterm the first steps of `path` lead to, if `self` has
+    /// one there, and the rest of `path`.
+    fn child<'a>(&self, path: &'a [Step]) -> Option<(&Exp, &'a [Step])> {
+        match (self, path) {
+            (Exp::Apply(f, _), [Step::ApplyFun, rest @ ..]) => Some((f, rest)),
+            (Exp::Apply(_, x), [Step::ApplyArg, rest @ ..]) => Some((x, rest)),
+            (Exp::Lambda(_, body), [Step::LambdaBody, rest @ ..])
+            | (Exp::Rec(_, body), [Step::RecBody, rest @ ..]) => Some((body, rest)),
+            (Exp::Case(e, _), [Step::Scrutinee, rest @ ..]) => Some((e, rest)),
+            (Exp::Case(_, branches), [Step::Branch(i), Step::BranchBody, rest @ ..]) => {
+                Some((&branches.get(*i)?.expression, rest))
+            }
+            (Exp::Const(_, arguments), [Step::Argument(i), rest @ ..]) => {
+                Some((arguments.get(*i)?, rest))
+            }
+            _ => None,
+        }
+    }
+
+    fn child_mut<'a>(&mut self, path: &'a [Step]) -> Option<(&mut Exp, &'a [Step])> {
+        match (self, path) {
+            (Exp::Apply(f, _), [Step::ApplyFun, rest @ ..]) => Some((f, rest)),
+            (Exp::Apply(_, x), [Step::ApplyArg, rest @ ..]) => Some((x, rest)),
+            (Exp::Lambda(_, body), [Step::LambdaBody, rest @ ..])
+            | (Exp::Rec(_, body), [Step::RecBody, rest @ ..]) => Some((body, rest)),
+            (Exp::Case(e, _), [Step::Scrutinee, rest @ ..]) => Some((e, rest)),
+            (Exp::Case(_, branches), [Step::Branch(i), Step::BranchBody, rest @ ..]) => {
+                Some((&mut branches.get_mut(*i)?.expression, rest))
+            }
+            (Exp::Const(_, arguments), [Step::Argument(i), rest @ ..]) => {
+                Some((arguments.get_mut(*i)?, rest))
+            }
+            _ => None,
+        }
+    }
+
+    /// The subterm at `path`, if there is one.
+    pub fn get(&self, path: &[Step]) -> Option<&Exp> {
+        match path {
+            [] => Some(self),
+            _ => {
+                let (child, rest) = self.child(path)?;
+                child.get(rest)
+            }
+        }
+    }
+
+    pub fn get_mut(&mut self, path: &[Step]) -> Option<&mut Exp> {
+        match path {
+            [] => Some(self),
+            _ => {
+                let (child, rest) = self.child_mut(path)?;
+                child.get_mut(rest)
+            }
+        }
+    }
+
+    /// The branch at `path`, which ends with [`Step::Branch`], if there is
+    /// one.
+    pub fn branch(&self, path: &[Step]) -> Option<&Branch> {
+        let [case @ .., Step::Branch(i)] = path else {
+            return None;
+        };
+        match self.get(case)? {
+            Exp::Case(_, branches) => branches.get(*i),
+            _ => None,
+        }
+    }
+
+    /// Puts `new` in place of the subterm at `path`, returning the subterm, or
+    /// gives `new` back if there is nothing at `path`.
+    pub fn replace(&mut self, path: &[Step], new: Exp) -> Result<Exp, Exp> {
+        match self.get_mut(path) {
+            Some(exp) => Ok(std::mem::replace(exp, new)),
+            None => Err(new),
+        }
+    }
+
+    /// The number of subterms of `self`, itself included.
+    pub fn size(&self) -> usize {
+        1 + self
+            .children()
+            .into_iter()
+            .map(|(_, child)| child.size())
+            .sum::<usize>()
+    }
+
+    /// Every subterm of `self`, itself included, with its path, parents
+    /// before their children.
+    pub fn subterms(&self) -> Vec<(Path, &Exp)> {
+        let mut subterms = vec![];
+        let mut stack = vec![(vec![], self)];
+        while let Some((path, exp)) = stack.pop() {
+            for (steps, child) in exp.children().into_iter().rev() {
+                let mut path: Path = path.clone();
+                path.extend(steps);
+                stack.push((path, child));
+            }
+            subterms.push((path, exp));
+        }
+        subterms
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{concrete, Exp, Step::*};
+
+    const CODE: &str = "λx. case x of { C() → f x; D(y) → E(y, x) }";
+
+    fn exp() -> Exp {
+        concrete::parse(CODE).unwrap()
+    }
+
+    #[test]
+    fn test_subterms() {
+        let exp = exp();
+        let subterms: Vec<_> = exp
+            .subterms()
+            .into_iter()
+            .map(|(path, exp)| (path, concrete::format(exp)))
+            .collect();
+        assert_eq!(subterms.len(), exp.size());
+        assert_eq!(subterms[0], (vec![], concrete::format(&exp)));
+        assert_eq!(subterms[1].0, vec![LambdaBody]);
+        assert_eq!(subterms[2], (vec![LambdaBody, Scrutinee], "x".to_string()));
+        assert_eq!(
+            subterms[3],
+            (vec![LambdaBody, Branch(0), BranchBody], "f x".to_string())
+        );
+        assert_eq!(
+            subterms[8],
+            (
+                vec![LambdaBody, Branch(1), BranchBody, Argument(1)],
+                "x".to_string()
+            )
+        );
+        for (path, subterm) in &subterms {
+            assert_eq!(&concrete::format(exp.get(path).unwrap()), subterm);
+        }
+    }
+
+    #[test]
+    fn test_get() {
+        let exp = exp();
+        assert_eq!(exp.get(&[]), Some(&exp));
+        assert_eq!(
+            exp.get(&[LambdaBody, Branch(0), BranchBody, ApplyFun]),
+            Some(&Exp::Var("f".to_string()))
+        );
+        assert_eq!(exp.get(&[ApplyFun]), None);
+        assert_eq!(exp.get(&[LambdaBody, Branch(2), BranchBody]), None);
+        assert_eq!(exp.get(&[LambdaBody, Scrutinee, ApplyFun]), None);
+        assert_eq!(exp.get(&[LambdaBody, BranchBody]), None);
+    }
+
+    #[test]
+    fn test_branch() {
+        let exp = exp();
+        // A path ending at a branch does not lead to an expression.
+        assert_eq!(exp.get(&[LambdaBody, Branch(1)]), None);
+        assert_eq!(
+            exp.branch(&[LambdaBody, Branch(1)])
+                .map(|branch| &branch.parameters),
+            Some(&vec!["y".to_string()])
+        );
+        assert_eq!(exp.branch(&[LambdaBody, Branch(2)]), None);
+        assert_eq!(exp.branch(&[Branch(0)]), None);
+        assert_eq!(exp.branch(&[LambdaBody]), None);
+    }
+
+    #[test]
+    fn test_replace() {
+        let mut exp = exp();
+        let old = exp.replace(
+            &[LambdaBody, Branch(1), BranchBody, Argument(1)],
+            concrete::parse("y").unwrap(),
+        );
+        assert_eq!(old, Ok(concrete::parse("x").unwrap()));
+        assert_eq!(
+            exp,
+            concrete::parse("λx. case x of { C() → f x; D(y) → E(y, y) }").unwrap()
+        );
+        let new = concrete::parse("z").unwrap();
+        assert_eq!(exp.replace(&[ApplyArg], new.clone()), Err(new.clone()));
+        // A branch is not an expression to put `new` in place of.
+        assert_eq!(
+            exp.replace(&[LambdaBody, Branch(0)], new.clone()),
+            Err(new.clone())
+        );
+        let whole = exp.clone();
+        assert_eq!(exp.replace(&[], new.clone()), Ok(whole));
+        assert_eq!(exp, new);
+    }
+}
@@ -0,0 +1,278 @@
+use std::ops::Range;
+
+use chi::{
+    bootstrapping::{self, Context},
+    semantic,
+    syntax::{
+        abst,
+        concrete::{self, Span, SpanKind, SyntaxError},
+        Exp,
+    },
+};
+use lsp_types::{Diagnostic, DiagnosticSeverity, DocumentSymbol, Position, SymbolKind, TextEdit};
+
+/// An open χ file and the result of parsing it.
+pub struct Document {
+    text: String,
+    parsed: Result<(Exp, Vec<Span>), SyntaxError>,
+}
+
+impl Document {
+    pub fn new(text: String) -> Self {
+        let parsed = concrete::parse_spanned(&text);
+        Self { text, parsed }
+    }
+
+    /// The byte offset of `position`, whose character is counted in UTF-16
+    /// code units as the protocol demands.
+    pub fn offset(&self, position: Position) -> usize {
+        let mut line_start = 0;
+        for _ in 0..position.line {
+            match self.text[line_start..].find('\n') {
+                Some(end) => line_start += end + 1,
+                None => return self.text.len(),
+            }
+        }
+        let mut units = 0;
+        for (index, c) in self.text[line_start..].char_indices() {
+            if units >= position.character as usize || c == '\n' {
+                return line_start + index;
+            }
+            units += c.len_utf16();
+        }
+        self.text.len()
+    }
+
+    pub fn position(&self, offset: usize) -> Position {
+        let before = &self.text[..offset];
+        let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+        Position {
+            line: before.matches('\n').count() as u32,
+            character: before[line_start..].encode_utf16().count() as u32,
+        }
+    }
+
+    pub fn range(&self, range: Range<usize>) -> lsp_types::Range {
+        lsp_types::Range {
+            start: self.position(range.start),
+            end: self.position(range.end),
+        }
+    }
+
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        let Err(SyntaxError { offset }) = self.parsed else {
+            return vec![];
+        };
+        let (end, message) = match self.text[offset..].chars().next() {
+            Some(c) => (offset + c.len_utf8(), format!("unexpected `{}`", c)),
+            None => (offset, "unexpected end of input".to_string()),
+        };
+        vec![Diagnostic {
+            range: self.range(offset..end),
+            severity: Some(DiagnosticSeverity::ERROR),
+            source: Some("chi".to_string()),
+            message,
+            ..Diagnostic::default()
+        }]
+    }
+
+    /// Replaces the whole text with its formatted version, unless it is
+    /// formatted already or does not parse.
+    pub fn format(&self) -> Option<Vec<TextEdit>> {
+        let (exp, _) = self.parsed.as_ref().ok()?;
+        let formatted = format!("{}\n", concrete::format(exp));
+        if formatted == self.text {
+            return Some(vec![]);
+        }
+        Some(vec![TextEdit {
+            range: self.range(0..self.text.len()),
+            new_text: formatted,
+        }])
+    }
+
+    fn spans(&self) -> &[Span] {
+        self.parsed.as_ref().map_or(&[], |(_, spans)| spans)
+    }
+
+    /// The spans containing `offset`, innermost first.
+    fn enclosing(&self, offset: usize) -> impl Iterator<Item = &Span> {
+        self.spans()
+            .iter()
+            .rev()
+            .filter(move |span| span.range.contains(&offset))
+    }
+
+    fn subterm(&self, span: &Span) -> Option<Exp> {
+        concrete::parse(&self.text[span.range.clone()]).ok()
+    }
+
+    /// The innermost subterm at `offset` and its span.
+    fn subterm_at(&self, offset: usize) -> Option<(Exp, Range<usize>)> {
+        let span = self
+            .enclosing(offset)
+            .find(|span| span.kind == SpanKind::Expression)?;
+        Some((self.subterm(span)?, span.range.clone()))
+    }
+
+    /// The subterm at `offset` and its abstract syntax and standard form.
+    pub fn hover(&self, offset: usize) -> Option<(String, Range<usize>)> {
+        let (exp, range) = self.subterm_at(offset)?;
+        let standard = bootstrapping::decompile(&exp, &mut Context::default());
+        let markdown = format!(
+            "```\n{}\n```\n\nStandard form:\n```\n{}\n```",
+            abst::format(&exp),
+            concrete::format(&standard)
+        );
+        Some((markdown, range))
+    }
+
+    /// The range of the name `exp`, a λ or `rec` spanning `range`, binds.
+    fn binder(&self, exp: &Exp, range: &Range<usize>) -> Option<(String, Range<usize>)> {
+        let (name, keyword) = match exp {
+            Exp::Lambda(name, _) => (name, ["\\", "λ", "𝜆"].as_slice()),
+            Exp::Rec(name, _) => (name, ["rec"].as_slice()),
+            _ => return None,
+        };
+        let text = &self.text[range.clone()];
+        let rest = keyword
+            .iter()
+            .find_map(|keyword| text.strip_prefix(keyword))?;
+        let start = range.end - rest.trim_start().len();
+        Some((name.clone(), start..start + name.len()))
+    }
+
+    /// The parameters of the branch spanning `range` and their ranges, and
+    /// where the parameter list ends.
+    fn parameters(&self, range: &Range<usize>) -> (Vec<(String, Range<usize>)>, usize) {
+        let text = &self.text[range.clone()];
+        let (Some(open), Some(close)) = (text.find('('), text.find(')')) else {
+            return (vec![], range.start);
+        };
+        let mut start = range.start + open + 1;
+        let mut parameters = vec![];
+        for parameter in text[open + 1..close].split(',') {
+            let name = parameter.trim();
+            let name_start = start + parameter.find(name).unwrap_or(0);
+            if !name.is_empty() {
+                parameters.push((name.to_string(), name_start..name_start + name.len()));
+            }
+            start += parameter.len() + 1;
+        }
+        (parameters, range.start + close + 1)
+    }
+
+    /// Where the variable at `offset` is bound, if it is bound at all.
+    pub fn binder_of(&self, offset: usize) -> Option<Range<usize>> {
+        let (Exp::Var(variable), _) = self.subterm_at(offset)? else {
+            return None;
+        };
+        self.enclosing(offset).find_map(|span| match span.kind {
+            SpanKind::Expression => {
+                let (name, binder) = self.binder(&self.subterm(span)?, &span.range)?;
+                (name == variable && binder.end <= offset).then_some(binder)
+            }
+            SpanKind::Branch => {
+                let (parameters, end) = self.parameters(&span.range);
+                parameters
+                    .into_iter()
+                    .rev()
+                    .find(|(name, _)| *name == variable && end <= offset)
+                    .map(|(_, binder)| binder)
+            }
+        })
+    }
+
+    /// The ranges of all binders, in the order [`semantic::resolve`] lists
+    /// them.
+    fn binders(&self) -> Vec<Range<usize>> {
+        let mut binders: Vec<Range<usize>> = self
+            .spans()
+            .iter()
+            .flat_map(|span| match span.kind {
+                SpanKind::Expression => self
+                    .subterm(span)
+                    .and_then(|exp| self.binder(&exp, &span.range))
+                    .map(|(_, binder)| vec![binder])
+                    .unwrap_or_default(),
+                SpanKind::Branch => {
+                    let (parameters, _) = self.parameters(&span.range);
+                    parameters.into_iter().map(|(_, binder)| binder).collect()
+                }
+            })
+            .collect();
+        binders.sort_by_key(|binder| binder.start);
+        binders
+    }
+
+    /// The ranges of all variable occurrences, in the order of their paths.
+    fn occurrences(&self) -> Vec<Range<usize>> {
+        self.spans()
+            .iter()
+            .filter(|span| {
+                span.kind == SpanKind::Expression
+                    && !self.text[span.range.clone()].starts_with('(')
+                    && matches!(self.subterm(span), Some(Exp::Var(_)))
+            })
+            .map(|span| span.range.clone())
+            .collect()
+    }
+
+    /// The ranges to replace with `new_name` to rename the variable at
+    /// `offset`, an occurrence or a binder.
+    pub fn rename(&self, offset: usize, new_name: &str) -> Result<Vec<Range<usize>>, String> {
+        let Ok((exp, _)) = &self.parsed else {
+            return Err("the document does not parse".to_string());
+        };
+        let binders = self.binders();
+        let binder = self.binder_of(offset).unwrap_or(offset..offset);
+        let id = binders
+            .iter()
+            .position(|range| range.start <= binder.start && binder.start < range.end)
+            .ok_or_else(|| "no bound variable here".to_string())?;
+        let info = semantic::resolve(exp);
+        semantic::rename_binder(exp, &info.binders[id].at, new_name)
+            .map_err(|error| error.to_string())?;
+
+        let mut occurrences: Vec<_> = info
+            .bound
+            .iter()
+            .map(|(path, binder)| (path, Some(*binder)))
+            .chain(info.free.iter().map(|(path, _)| (path, None)))
+            .collect();
+        occurrences.sort();
+        let mut ranges = vec![binders[id].clone()];
+        ranges.extend(
+            Iterator::zip(occurrences.iter(), self.occurrences())
+                .filter(|((_, binder), _)| *binder == Some(id))
+                .map(|(_, range)| range),
+        );
+        Ok(ranges)
+    }
+
+    /// The `rec` definitions of the document. A parenthesised definition
+    /// also has a span without the parentheses, and only that one counts.
+    #[allow(deprecated)]
+    pub fn symbols(&self) -> Vec<DocumentSymbol> {
+        self.spans()
+            .iter()
+            .filter(|span| span.kind == SpanKind::Expression)
+            .filter_map(|span| {
+                let exp = self.subterm(span)?;
+                let Exp::Rec(_, _) = exp else {
+                    return None;
+                };
+                let (name, binder) = self.binder(&exp, &span.range)?;
+                Some(DocumentSymbol {
+                    name,
+                    detail: None,
+                    kind: SymbolKind::FUNCTION,
+                    tags: None,
+                    deprecated: None,
+                    range: self.range(span.range.clone()),
+                    selection_range: self.range(binder),
+                    children: None,
+                })
+            })
+            .collect()
+    }
+}
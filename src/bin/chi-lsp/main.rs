@@ -0,0 +1,208 @@
+//! A language server for concrete χ syntax, speaking the protocol over stdio.
+
+use std::{collections::HashMap, error::Error};
+
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::{
+    notification::{
+        DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, Notification as _,
+        PublishDiagnostics,
+    },
+    request::{
+        DocumentSymbolRequest, Formatting, GotoDefinition, HoverRequest, Rename, Request as _,
+    },
+    DidChangeTextDocumentParams, DidCloseTextDocumentParams, DidOpenTextDocumentParams,
+    DocumentFormattingParams, DocumentSymbolParams, DocumentSymbolResponse, GotoDefinitionParams,
+    GotoDefinitionResponse, Hover, HoverContents, HoverParams, HoverProviderCapability, Location,
+    MarkupContent, MarkupKind, OneOf, PublishDiagnosticsParams, RenameParams, ServerCapabilities,
+    TextDocumentSyncCapability, TextDocumentSyncKind, TextEdit, Uri, WorkspaceEdit,
+};
+
+use document::Document;
+
+mod document;
+
+#[derive(Default)]
+struct Server {
+    documents: HashMap<Uri, Document>,
+}
+
+fn capabilities() -> ServerCapabilities {
+    ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        definition_provider: Some(OneOf::Left(true)),
+        document_formatting_provider: Some(OneOf::Left(true)),
+        document_symbol_provider: Some(OneOf::Left(true)),
+        rename_provider: Some(OneOf::Left(true)),
+        ..ServerCapabilities::default()
+    }
+}
+
+impl Server {
+    fn hover(&self, params: HoverParams) -> Option<Hover> {
+        let position = params.text_document_position_params;
+        let document = self.documents.get(&position.text_document.uri)?;
+        let (markdown, range) = document.hover(document.offset(position.position))?;
+        Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: markdown,
+            }),
+            range: Some(document.range(range)),
+        })
+    }
+
+    fn definition(&self, params: GotoDefinitionParams) -> Option<GotoDefinitionResponse> {
+        let position = params.text_document_position_params;
+        let uri = position.text_document.uri;
+        let document = self.documents.get(&uri)?;
+        let binder = document.binder_of(document.offset(position.position))?;
+        Some(GotoDefinitionResponse::Scalar(Location {
+            uri,
+            range: document.range(binder),
+        }))
+    }
+
+    fn formatting(&self, params: DocumentFormattingParams) -> Option<Vec<TextEdit>> {
+        self.documents.get(&params.text_document.uri)?.format()
+    }
+
+    fn symbols(&self, params: DocumentSymbolParams) -> Option<DocumentSymbolResponse> {
+        let document = self.documents.get(&params.text_document.uri)?;
+        Some(DocumentSymbolResponse::Nested(document.symbols()))
+    }
+
+    fn rename(&self, params: RenameParams) -> Result<WorkspaceEdit, String> {
+        let position = params.text_document_position;
+        let uri = position.text_document.uri;
+        let document = self
+            .documents
+            .get(&uri)
+            .ok_or_else(|| "unknown document".to_string())?;
+        let new_name = params.new_name;
+        let ranges = document.rename(document.offset(position.position), &new_name)?;
+        let edits = ranges
+            .into_iter()
+            .map(|range| TextEdit {
+                range: document.range(range),
+                new_text: new_name.clone(),
+            })
+            .collect();
+        Ok(WorkspaceEdit {
+            changes: Some(HashMap::from([(uri, edits)])),
+            ..WorkspaceEdit::default()
+        })
+    }
+
+    fn handle_request(&self, request: Request) -> Response {
+        match request.method.as_str() {
+            HoverRequest::METHOD => respond::<HoverRequest>(request, |params| self.hover(params)),
+            GotoDefinition::METHOD => {
+                respond::<GotoDefinition>(request, |params| self.definition(params))
+            }
+            Formatting::METHOD => respond::<Formatting>(request, |params| self.formatting(params)),
+            DocumentSymbolRequest::METHOD => {
+                respond::<DocumentSymbolRequest>(request, |params| self.symbols(params))
+            }
+            Rename::METHOD => {
+                let id = request.id.clone();
+                match serde_json::from_value(request.params) {
+                    Ok(params) => match self.rename(params) {
+                        Ok(edit) => Response::new_ok(id, edit),
+                        Err(message) => {
+                            Response::new_err(id, ErrorCode::RequestFailed as i32, message)
+                        }
+                    },
+                    Err(error) => {
+                        Response::new_err(id, ErrorCode::InvalidParams as i32, error.to_string())
+                    }
+                }
+            }
+            _ => Response::new_err(
+                request.id,
+                ErrorCode::MethodNotFound as i32,
+                format!("unknown request {}", request.method),
+            ),
+        }
+    }
+
+    /// Updates the documents, returning the diagnostics to publish.
+    fn handle_notification(
+        &mut self,
+        notification: Notification,
+    ) -> Option<PublishDiagnosticsParams> {
+        let (uri, document) = match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params: DidOpenTextDocumentParams =
+                    serde_json::from_value(notification.params).ok()?;
+                let document = params.text_document;
+                (document.uri, Document::new(document.text))
+            }
+            DidChangeTextDocument::METHOD => {
+                let params: DidChangeTextDocumentParams =
+                    serde_json::from_value(notification.params).ok()?;
+                // The whole text is sent on every change, as announced.
+                let change = params.content_changes.into_iter().last()?;
+                (params.text_document.uri, Document::new(change.text))
+            }
+            DidCloseTextDocument::METHOD => {
+                let params: DidCloseTextDocumentParams =
+                    serde_json::from_value(notification.params).ok()?;
+                let uri = params.text_document.uri;
+                self.documents.remove(&uri);
+                return Some(PublishDiagnosticsParams::new(uri, vec![], None));
+            }
+            _ => return None,
+        };
+        let diagnostics = document.diagnostics();
+        self.documents.insert(uri.clone(), document);
+        Some(PublishDiagnosticsParams::new(uri, diagnostics, None))
+    }
+
+    fn run(&mut self, connection: &Connection) -> Result<(), Box<dyn Error + Send + Sync>> {
+        for message in &connection.receiver {
+            match message {
+                Message::Request(request) => {
+                    if connection.handle_shutdown(&request)? {
+                        return Ok(());
+                    }
+                    let response = self.handle_request(request);
+                    connection.sender.send(response.into())?;
+                }
+                Message::Notification(notification) => {
+                    if let Some(params) = self.handle_notification(notification) {
+                        let notification =
+                            Notification::new(PublishDiagnostics::METHOD.to_string(), params);
+                        connection.sender.send(notification.into())?;
+                    }
+                }
+                Message::Response(_) => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+fn respond<R: lsp_types::request::Request>(
+    request: Request,
+    handle: impl FnOnce(R::Params) -> R::Result,
+) -> Response {
+    match serde_json::from_value(request.params) {
+        Ok(params) => Response::new_ok(request.id, handle(params)),
+        Err(error) => Response::new_err(
+            request.id,
+            ErrorCode::InvalidParams as i32,
+            error.to_string(),
+        ),
+    }
+}
+
+fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
+    let (connection, io_threads) = Connection::stdio();
+    connection.initialize(serde_json::to_value(capabilities())?)?;
+    Server::default().run(&connection)?;
+    drop(connection);
+    io_threads.join()?;
+    Ok(())
+}
@@ -0,0 +1,356 @@
+use std::{
+    fmt, fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    process::ExitCode,
+    thread,
+};
+
+use chi::{
+    bootstrapping::{self, Context},
+    semantic::Evaluator,
+    syntax::{self, abst, concrete, diff, Exp, Syntax},
+};
+use clap::{Parser, Subcommand};
+
+mod repl;
+
+/// Evaluation finished without a value within the step limit, `fmt --check`
+/// found a file which is not formatted or `diff` found differences.
+const EXIT_FAILURE: u8 = 1;
+/// A program could not be parsed, as `EX_DATAERR` of sysexits(3).
+const EXIT_DATA: u8 = 65;
+/// Evaluation panicked, as `EX_SOFTWARE`.
+const EXIT_SOFTWARE: u8 = 70;
+/// A file could not be read or written, as `EX_IOERR`.
+const EXIT_IO: u8 = 74;
+
+/// Substitution and the self-interpreter recurse once per level of nesting,
+/// so deep programs need a larger stack than the main thread has. [`run`]
+/// keeps the stack of the evaluation on the heap with [`Evaluator`] instead,
+/// so that a program which does not terminate uses up the step limit and not
+/// the stack.
+const STACK_SIZE: usize = 1 << 30;
+
+#[derive(Parser)]
+#[command(name = "chi", version, about = "Run, format and code χ programs")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Evaluate a program.
+    Run {
+        /// The program, in concrete or abstract syntax, or `-` for stdin.
+        file: PathBuf,
+        /// Give up after this many evaluation steps.
+        #[arg(long, default_value_t = 10_000_000)]
+        limit: usize,
+        #[arg(long, value_enum, default_value_t = Syntax::Concrete)]
+        output: Syntax,
+    },
+    /// Format a program in place.
+    Fmt {
+        file: PathBuf,
+        /// The syntax to format in, by default the one the file is written in.
+        #[arg(long, value_enum)]
+        syntax: Option<Syntax>,
+        /// Only check whether the file is formatted.
+        #[arg(long)]
+        check: bool,
+    },
+    /// Print a program in the other syntax, or the one given.
+    Convert {
+        file: PathBuf,
+        #[arg(long, value_enum)]
+        to: Option<Syntax>,
+    },
+    /// Print the standard form of a program.
+    Decompile {
+        file: PathBuf,
+        #[command(flatten)]
+        context: ContextFile,
+        #[arg(long, value_enum, default_value_t = Syntax::Concrete)]
+        output: Syntax,
+    },
+    /// Evaluate a program with the self-interpreter.
+    SelfEval {
+        file: PathBuf,
+        /// Give up after this many evaluation steps of the self-interpreter.
+        #[arg(long, default_value_t = 10_000_000)]
+        limit: usize,
+        #[command(flatten)]
+        context: ContextFile,
+        #[arg(long, value_enum, default_value_t = Syntax::Concrete)]
+        output: Syntax,
+    },
+    /// Show the smallest subterms in which two programs differ.
+    Diff {
+        left: PathBuf,
+        right: PathBuf,
+        /// Ignore the names of binders.
+        #[arg(long)]
+        alpha: bool,
+        /// Show the subterms in two columns instead of one after the other.
+        #[arg(long)]
+        side_by_side: bool,
+    },
+    /// Start an interactive session.
+    Repl {
+        /// Give up evaluating an input after this many steps.
+        #[arg(long, default_value_t = 10_000_000)]
+        limit: usize,
+    },
+    /// Substitute an expression for a variable with the χ implementation of
+    /// substitution.
+    SelfSubst {
+        file: PathBuf,
+        variable: String,
+        /// The expression substituted, in concrete or abstract syntax.
+        expression: String,
+        #[command(flatten)]
+        context: ContextFile,
+        #[arg(long, value_enum, default_value_t = Syntax::Concrete)]
+        output: Syntax,
+    },
+}
+
+#[derive(clap::Args)]
+struct ContextFile {
+    /// A JSON file with the numbering of variables and constructors, which is
+    /// read if it exists and updated with the numbers given to new names.
+    #[arg(long)]
+    context: Option<PathBuf>,
+}
+
+#[derive(Debug)]
+enum Error {
+    Io(PathBuf, io::Error),
+    Parse(String),
+    Context(PathBuf, serde_json::Error),
+    Panicked,
+    Terminal(rustyline::error::ReadlineError),
+}
+
+impl Error {
+    fn exit_code(&self) -> u8 {
+        match self {
+            Error::Io(_, _) => EXIT_IO,
+            Error::Parse(_) | Error::Context(_, _) => EXIT_DATA,
+            Error::Panicked => EXIT_SOFTWARE,
+            Error::Terminal(_) => EXIT_IO,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(path, error) => write!(f, "{}: {}", path.display(), error),
+            Error::Parse(source) => {
+                write!(f, "{}: not valid concrete or abstract syntax", source)
+            }
+            Error::Context(path, error) => write!(f, "{}: {}", path.display(), error),
+            Error::Panicked => write!(f, "evaluation failed"),
+            Error::Terminal(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+fn read(path: &Path) -> Result<String, Error> {
+    let mut code = String::new();
+    let result = if path == Path::new("-") {
+        io::stdin().read_to_string(&mut code).map(|_| ())
+    } else {
+        fs::read_to_string(path).map(|read| code = read)
+    };
+    result.map_err(|error| Error::Io(path.to_path_buf(), error))?;
+    Ok(code)
+}
+
+/// Parses `code` in either syntax, see [`syntax::parse`].
+fn parse(code: &str, source: &str) -> Result<(Exp, Syntax), Error> {
+    syntax::parse(code).map_err(|_| Error::Parse(source.to_string()))
+}
+
+fn parse_file(path: &Path) -> Result<(Exp, Syntax), Error> {
+    parse(&read(path)?, &path.display().to_string())
+}
+
+fn format(exp: &Exp, syntax: Syntax) -> String {
+    match syntax {
+        Syntax::Concrete => concrete::format(exp),
+        Syntax::Abstract => abst::format(exp),
+    }
+}
+
+fn on_large_stack<T: Send + 'static>(f: impl FnOnce() -> T + Send + 'static) -> Result<T, Error> {
+    thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(f)
+        .expect("failed to spawn the evaluation thread")
+        .join()
+        .map_err(|_| Error::Panicked)
+}
+
+impl ContextFile {
+    fn load(&self) -> Result<Context, Error> {
+        match &self.context {
+            Some(path) if path.exists() => serde_json::from_str(&read(path)?)
+                .map_err(|error| Error::Context(path.clone(), error)),
+            _ => Ok(Context::default()),
+        }
+    }
+
+    fn save(&self, context: &Context) -> Result<(), Error> {
+        let Some(path) = &self.context else {
+            return Ok(());
+        };
+        let json = serde_json::to_string_pretty(context).unwrap();
+        fs::write(path, json).map_err(|error| Error::Io(path.clone(), error))
+    }
+
+    /// Runs `f` with the loaded context on a large stack and saves the
+    /// context afterwards.
+    fn with<T: Send + 'static>(
+        &self,
+        f: impl FnOnce(&mut Context) -> T + Send + 'static,
+    ) -> Result<T, Error> {
+        let mut context = self.load()?;
+        let (result, context) = on_large_stack(move || (f(&mut context), context))?;
+        self.save(&context)?;
+        Ok(result)
+    }
+}
+
+/// Evaluates `exp` and prints its value, or reports that it took more than
+/// `limit` steps.
+fn run(exp: Exp, limit: usize, output: Syntax) -> Result<u8, Error> {
+    let value = on_large_stack(move || {
+        let mut evaluator = Evaluator::start(&exp);
+        evaluator.run(limit);
+        evaluator.result().cloned()
+    })?;
+    match value {
+        Some(value) => {
+            println!("{}", format(&value, output));
+            Ok(0)
+        }
+        None => {
+            eprintln!("chi: evaluation did not finish within {} steps", limit);
+            Ok(EXIT_FAILURE)
+        }
+    }
+}
+
+fn execute(command: Command) -> Result<u8, Error> {
+    match command {
+        Command::Run {
+            file,
+            limit,
+            output,
+        } => {
+            let (exp, _) = parse_file(&file)?;
+            return run(exp, limit, output);
+        }
+        Command::Fmt {
+            file,
+            syntax,
+            check,
+        } => {
+            let code = read(&file)?;
+            let (exp, detected) = parse(&code, &file.display().to_string())?;
+            let formatted = format!("{}\n", format(&exp, syntax.unwrap_or(detected)));
+            if check {
+                if code != formatted {
+                    eprintln!("chi: {} is not formatted", file.display());
+                    return Ok(EXIT_FAILURE);
+                }
+            } else if file == Path::new("-") {
+                print!("{}", formatted);
+            } else if code != formatted {
+                fs::write(&file, formatted).map_err(|error| Error::Io(file.clone(), error))?;
+            }
+        }
+        Command::Convert { file, to } => {
+            let (exp, detected) = parse_file(&file)?;
+            let to = to.unwrap_or(match detected {
+                Syntax::Concrete => Syntax::Abstract,
+                Syntax::Abstract => Syntax::Concrete,
+            });
+            println!("{}", format(&exp, to));
+        }
+        Command::Decompile {
+            file,
+            context,
+            output,
+        } => {
+            let (exp, _) = parse_file(&file)?;
+            let result = context.with(move |context| bootstrapping::decompile(&exp, context))?;
+            println!("{}", format(&result, output));
+        }
+        Command::SelfEval {
+            file,
+            limit,
+            context,
+            output,
+        } => {
+            let (exp, _) = parse_file(&file)?;
+            let interpretation =
+                context.with(move |context| bootstrapping::self_interpretation(&exp, context))?;
+            return run(interpretation, limit, output);
+        }
+        Command::Diff {
+            left,
+            right,
+            alpha,
+            side_by_side,
+        } => {
+            let (left, _) = parse_file(&left)?;
+            let (right, _) = parse_file(&right)?;
+            let differences = if alpha {
+                diff::diff_modulo_alpha(&left, &right)
+            } else {
+                diff::diff(&left, &right)
+            };
+            if side_by_side {
+                print!("{}", differences.side_by_side());
+            } else {
+                print!("{}", differences);
+            }
+            if !differences.is_empty() {
+                return Ok(EXIT_FAILURE);
+            }
+        }
+        Command::Repl { limit } => {
+            on_large_stack(move || repl::run(limit))?.map_err(Error::Terminal)?;
+        }
+        Command::SelfSubst {
+            file,
+            variable,
+            expression,
+            context,
+            output,
+        } => {
+            let (exp, _) = parse_file(&file)?;
+            let (to, _) = parse(&expression, "expression")?;
+            let result = context.with(move |context| {
+                bootstrapping::self_substitute(&variable, &to, &exp, context)
+            })?;
+            println!("{}", format(&result, output));
+        }
+    }
+    Ok(0)
+}
+
+fn main() -> ExitCode {
+    match execute(Cli::parse().command) {
+        Ok(code) => ExitCode::from(code),
+        Err(error) => {
+            eprintln!("chi: {}", error);
+            ExitCode::from(error.exit_code())
+        }
+    }
+}
@@ -0,0 +1,415 @@
+use std::{
+    collections::BTreeSet,
+    env, fs,
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+};
+
+use chi::{
+    bootstrapping::{self, Context},
+    lowering::scott::Signature,
+    semantic::{self, Evaluator, Statistics, Status},
+    syntax::{abst, concrete, is_variable_name, Exp, Variable},
+};
+use rustyline::{
+    completion::{Completer, Pair},
+    error::ReadlineError,
+    highlight::Highlighter,
+    hint::Hinter,
+    history::DefaultHistory,
+    validate::{ValidationContext, ValidationResult, Validator},
+    Editor, Helper,
+};
+
+use crate::parse;
+
+const COMMANDS: [&str; 9] = [
+    ":abs", ":std", ":subst", ":self", ":trace", ":load", ":defs", ":help", ":quit",
+];
+
+const HELP: &str = "\
+name = e        define name, which is replaced by e in later input
+e               evaluate e
+:abs e          show e in abstract syntax
+:std e          show the standard form of e and the numbering used so far
+:subst x e      substitute e for x in the last expression
+:self e         evaluate e with the self-interpreter
+:trace e        evaluate e, showing every reduction
+:load file [x]  define x, by default the file name, as the program in file
+:defs           list the definitions
+:quit           leave";
+
+/// How many reductions `:trace` shows before eliding the rest.
+const TRACE_LENGTH: usize = 100;
+
+/// The state kept between inputs of the REPL.
+pub struct Session {
+    /// Definitions with the earlier ones already substituted in.
+    definitions: Vec<(Variable, Exp)>,
+    constructors: BTreeSet<String>,
+    context: Context,
+    last: Option<Exp>,
+    step_limit: usize,
+}
+
+/// Whether every brace and parenthesis opened in `input` is closed.
+fn is_complete(input: &str) -> bool {
+    let depth = |open, close| {
+        input.chars().filter(|&c| c == open).count() as isize
+            - input.chars().filter(|&c| c == close).count() as isize
+    };
+    depth('{', '}') <= 0 && depth('(', ')') <= 0
+}
+
+impl Session {
+    pub fn new(step_limit: usize) -> Self {
+        Self {
+            definitions: vec![],
+            constructors: BTreeSet::new(),
+            context: Context::default(),
+            last: None,
+            step_limit,
+        }
+    }
+
+    /// The defined names and the constructors seen so far.
+    pub fn names(&self) -> impl Iterator<Item = &String> {
+        self.definitions
+            .iter()
+            .map(|(name, _)| name)
+            .chain(&self.constructors)
+    }
+
+    /// Parses `code` and replaces the defined names in it.
+    fn expression(&mut self, code: &str) -> Result<Exp, String> {
+        let (exp, _) = parse(code.trim(), "input").map_err(|error| error.to_string())?;
+        // constructors used with different arities are left to evaluation to
+        // complain about
+        if let Ok(signature) = Signature::of(&exp) {
+            let constructors = signature.constructors().iter();
+            self.constructors
+                .extend(constructors.map(|(constructor, _)| constructor.clone()));
+        }
+        Ok(self
+            .definitions
+            .iter()
+            .fold(exp, |exp, (name, definition)| {
+                semantic::substitute(&exp, name, definition)
+            }))
+    }
+
+    /// Evaluates `exp` with [`Evaluator`], whose stack is on the heap, as a
+    /// stack overflow cannot be caught and would end the session.
+    fn evaluate(&self, exp: &Exp) -> Result<Exp, String> {
+        let mut evaluator = Evaluator::start(exp);
+        panic::catch_unwind(AssertUnwindSafe(|| evaluator.run(self.step_limit)))
+            .map_err(|_| "evaluation failed".to_string())?;
+        evaluator
+            .result()
+            .cloned()
+            .ok_or_else(|| format!("evaluation did not finish within {} steps", self.step_limit))
+    }
+
+    fn define(&mut self, name: &str, code: &str) -> Result<String, String> {
+        let exp = self.expression(code)?;
+        let output = format!("{} = {}", name, concrete::format(&exp));
+        self.definitions.retain(|(defined, _)| defined != name);
+        self.definitions.push((name.to_string(), exp));
+        Ok(output)
+    }
+
+    fn load(&mut self, arguments: &str) -> Result<String, String> {
+        let mut arguments = arguments.split_whitespace();
+        let path = PathBuf::from(arguments.next().ok_or("usage: :load file [name]")?);
+        let stem = path.file_stem().and_then(|stem| stem.to_str());
+        let name = arguments.next().or(stem).unwrap_or_default().to_string();
+        if !is_variable_name(&name) {
+            return Err(format!(
+                "{} is not a valid name, give one after the file",
+                name
+            ));
+        }
+        let code = fs::read_to_string(&path).map_err(|error| error.to_string())?;
+        self.define(&name, &code)
+    }
+
+    /// Evaluates `exp` a step at a time, showing the term after every
+    /// reduction.
+    fn trace(&self, exp: &Exp) -> Result<String, String> {
+        fn reductions(statistics: Statistics) -> usize {
+            statistics.beta_reductions + statistics.branch_matches + statistics.rec_unfoldings
+        }
+        let mut steps = vec![];
+        let mut evaluator = Evaluator::start(exp);
+        let step_limit = self.step_limit;
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            while evaluator.steps() < step_limit {
+                let before = reductions(evaluator.statistics());
+                if evaluator.run(1) == Status::Done {
+                    break;
+                }
+                if reductions(evaluator.statistics()) == before {
+                    continue;
+                }
+                if steps.len() < TRACE_LENGTH {
+                    steps.push(format!("→ {}", concrete::format(&evaluator.current_term())));
+                } else {
+                    steps.push("…".to_string());
+                    evaluator.run(step_limit - evaluator.steps());
+                    break;
+                }
+            }
+        }));
+        let result = match (result, evaluator.result()) {
+            (Ok(()), Some(value)) => concrete::format(value),
+            (Ok(()), None) => format!("evaluation did not finish within {} steps", step_limit),
+            (Err(_), _) => "evaluation failed".to_string(),
+        };
+        steps.push(result);
+        Ok(steps.join("\n"))
+    }
+
+    fn standard_form(&mut self, exp: &Exp) -> String {
+        let result = bootstrapping::decompile(exp, &mut self.context);
+        let table = |names: Vec<(String, usize)>| {
+            names
+                .iter()
+                .map(|(name, id)| format!("{} = {}", name, id))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        format!(
+            "{}\nvariables: {}\nconstructors: {}",
+            concrete::format(&result),
+            table(self.context.variables()),
+            table(self.context.constructors())
+        )
+    }
+
+    /// Handles one complete input, returning what to print.
+    pub fn handle(&mut self, input: &str) -> Result<String, String> {
+        let input = input.trim();
+        let (command, rest) = match input.split_once(char::is_whitespace) {
+            Some((command, rest)) if command.starts_with(':') => (command, rest.trim()),
+            _ if input.starts_with(':') => (input, ""),
+            _ => ("", input),
+        };
+        match command {
+            "" => {
+                if let Some((name, code)) = rest.split_once('=') {
+                    if is_variable_name(name.trim()) {
+                        return self.define(name.trim(), code);
+                    }
+                }
+                let exp = self.expression(rest)?;
+                let value = self.evaluate(&exp)?;
+                self.last = Some(exp);
+                Ok(concrete::format(&value))
+            }
+            ":abs" => Ok(abst::format(&self.expression(rest)?)),
+            ":std" => {
+                let exp = self.expression(rest)?;
+                Ok(self.standard_form(&exp))
+            }
+            ":subst" => {
+                let (x, code) = rest
+                    .split_once(char::is_whitespace)
+                    .ok_or("usage: :subst x e")?;
+                let to = self.expression(code)?;
+                let last = self.last.as_ref().ok_or("there is no expression yet")?;
+                let result = semantic::substitute(last, x, &to);
+                let output = concrete::format(&result);
+                self.last = Some(result);
+                Ok(output)
+            }
+            ":self" => {
+                let exp = self.expression(rest)?;
+                let context = &mut self.context;
+                panic::catch_unwind(AssertUnwindSafe(|| {
+                    bootstrapping::self_interpret(&exp, context)
+                }))
+                .map(|result| concrete::format(&result))
+                .map_err(|_| "evaluation failed".to_string())
+            }
+            ":trace" => {
+                let exp = self.expression(rest)?;
+                self.trace(&exp)
+            }
+            ":load" => self.load(rest),
+            ":defs" => Ok(self
+                .definitions
+                .iter()
+                .map(|(name, exp)| format!("{} = {}", name, concrete::format(exp)))
+                .collect::<Vec<_>>()
+                .join("\n")),
+            ":help" => Ok(HELP.to_string()),
+            _ => Err(format!("unknown command {}, see :help", command)),
+        }
+    }
+}
+
+/// Completes names and commands, and asks for more lines while braces or
+/// parentheses are open.
+struct ChiHelper {
+    names: Vec<String>,
+}
+
+impl Completer for ChiHelper {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _: &rustyline::Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let start = line[..pos]
+            .rfind(|c: char| !(c.is_alphanumeric() || "_-':".contains(c)))
+            .map_or(0, |i| i + 1);
+        let word = &line[start..pos];
+        let candidates = COMMANDS
+            .iter()
+            .copied()
+            .chain(self.names.iter().map(String::as_str))
+            .filter(|name| !word.is_empty() && name.starts_with(word))
+            .map(|name| Pair {
+                display: name.to_string(),
+                replacement: name.to_string(),
+            })
+            .collect();
+        Ok((start, candidates))
+    }
+}
+
+impl Hinter for ChiHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ChiHelper {}
+
+impl Validator for ChiHelper {
+    fn validate(&self, context: &mut ValidationContext) -> rustyline::Result<ValidationResult> {
+        Ok(if is_complete(context.input()) {
+            ValidationResult::Valid(None)
+        } else {
+            ValidationResult::Incomplete
+        })
+    }
+}
+
+impl Helper for ChiHelper {}
+
+fn history_file() -> Option<PathBuf> {
+    env::var_os("HOME").map(|home| Path::new(&home).join(".chi_history"))
+}
+
+pub fn run(step_limit: usize) -> rustyline::Result<()> {
+    let mut editor: Editor<ChiHelper, DefaultHistory> = Editor::new()?;
+    editor.set_helper(Some(ChiHelper { names: vec![] }));
+    let history = history_file();
+    if let Some(history) = &history {
+        // there is no history on the first run
+        let _ = editor.load_history(history);
+    }
+    let mut session = Session::new(step_limit);
+    println!("χ REPL, :help for help");
+    loop {
+        let input = match editor.readline("χ> ") {
+            Ok(input) => input,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(error) => return Err(error),
+        };
+        if input.trim().is_empty() {
+            continue;
+        }
+        editor.add_history_entry(input.as_str())?;
+        if input.trim() == ":quit" {
+            break;
+        }
+        match session.handle(&input) {
+            Ok(output) => println!("{}", output),
+            Err(error) => eprintln!("error: {}", error),
+        }
+        editor.helper_mut().unwrap().names = session.names().cloned().collect();
+    }
+    if let Some(history) = &history {
+        editor.save_history(history)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session() {
+        let mut session = Session::new(1_000);
+        assert_eq!(session.handle("id = λx.x"), Ok("id = λx.x".to_string()));
+        assert_eq!(
+            session.handle("pair = λa.λb.Pair(id a, b)"),
+            Ok("pair = λa.λb.Pair((λx.x) a, b)".to_string())
+        );
+        assert_eq!(
+            session.handle("pair Zero() y"),
+            Ok("Pair(Zero(), y)".to_string())
+        );
+        assert_eq!(
+            session.handle(":subst y Two()"),
+            Ok("(λa.λb.Pair((λx.x) a, b)) Zero() Two()".to_string())
+        );
+        assert_eq!(
+            session.handle(":abs id"),
+            Ok("lambda x (var x)".to_string())
+        );
+        assert_eq!(
+            session.handle(":std Suc(x)"),
+            Ok(
+                "Const(Zero(), Cons(Var(Zero()), Nil()))\nvariables: x = 0\nconstructors: Suc = 0"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            session.handle(":self id Zero()"),
+            Ok("Const(Suc(Zero()), Nil())".to_string())
+        );
+        assert_eq!(
+            session.handle(":trace id Zero()"),
+            Ok("→ Zero()\nZero()".to_string())
+        );
+        assert!(session.handle("rec x = x").is_err());
+        assert_eq!(
+            session.handle(":trace rec x = x"),
+            Ok(format!(
+                "{}\n…\nevaluation did not finish within 1000 steps",
+                vec!["→ rec x = x"; TRACE_LENGTH].join("\n")
+            ))
+        );
+        assert!(session.handle("case C() of { C(x) → x }").is_err());
+        assert!(session.handle("rec = C()").is_err());
+        assert!(session.handle(":nothing").is_err());
+        let names: Vec<_> = session.names().collect();
+        assert_eq!(names, ["id", "pair", "Pair", "Suc", "Two", "Zero"]);
+    }
+
+    #[test]
+    fn test_load() {
+        let path = env::temp_dir().join(format!("twice{}.chi", std::process::id()));
+        fs::write(&path, "λf.λx.f (f x)").unwrap();
+        let mut session = Session::new(1_000);
+        let load = format!(":load {} twice", path.display());
+        assert!(session.handle(&load).is_ok());
+        assert_eq!(
+            session.handle("twice (λn.Suc(n)) Zero()"),
+            Ok("Suc(Suc(Zero()))".to_string())
+        );
+    }
+
+    #[test]
+    fn test_is_complete() {
+        assert!(is_complete("case x of { C() → x }"));
+        assert!(!is_complete("case x of {"));
+        assert!(!is_complete("Pair(x,"));
+    }
+}
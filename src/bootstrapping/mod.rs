@@ -1,15 +1,18 @@
 use std::sync::LazyLock;
 
 use bimap::BiMap;
+use chi_macros::include_chi;
 use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
 use wasm_bindgen::{prelude::wasm_bindgen, JsValue};
 
 use crate::{
+    convert::{FromChi, ToChi},
     semantic,
-    syntax::{concrete, Branch, Constructor, Exp, Variable},
+    syntax::{Branch, Constructor, Exp, Variable},
 };
 
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Context {
     variable: BiMap<Variable, usize>,
@@ -40,11 +43,29 @@ impl Context {
             id
         }
     }
+
+    /// The numbers given to variables so far, in increasing order.
+    pub fn variables(&self) -> Vec<(Variable, usize)> {
+        let mut result: Vec<_> = self.variable.iter().map(|(k, v)| (k.clone(), *v)).collect();
+        result.sort_by_key(|(_, id)| *id);
+        result
+    }
+
+    /// The numbers given to constructors so far, in increasing order.
+    pub fn constructors(&self) -> Vec<(Constructor, usize)> {
+        let mut result: Vec<_> = self
+            .constructor
+            .iter()
+            .map(|(k, v)| (k.clone(), *v))
+            .collect();
+        result.sort_by_key(|(_, id)| *id);
+        result
+    }
 }
 
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 impl Context {
-    #[wasm_bindgen(constructor)]
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
     pub fn new() -> Self {
         Self::default()
     }
@@ -78,27 +99,17 @@ impl Context {
             self.constructor.insert(constructor.clone(), id);
         }
     }
+}
 
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+impl Context {
     pub fn variable_assignments(&self) -> JsValue {
-        let result: Vec<_> = self.variable.iter().map(|(k, v)| (k.clone(), *v)).collect();
-        serde_wasm_bindgen::to_value(&result).unwrap()
+        serde_wasm_bindgen::to_value(&self.variables()).unwrap()
     }
 
     pub fn constructor_assignments(&self) -> JsValue {
-        let result: Vec<_> = self
-            .constructor
-            .iter()
-            .map(|(k, v)| (k.clone(), *v))
-            .collect();
-        serde_wasm_bindgen::to_value(&result).unwrap()
-    }
-}
-
-fn number_to_exp(number: usize) -> Exp {
-    if number == 0 {
-        Exp::Const("Zero".to_string(), vec![])
-    } else {
-        Exp::Const("Suc".to_string(), vec![number_to_exp(number - 1)])
+        serde_wasm_bindgen::to_value(&self.constructors()).unwrap()
     }
 }
 
@@ -106,25 +117,25 @@ fn decompile_list<T>(
     element_decompiler: impl Fn(&T, &mut Context) -> Exp + Copy,
 ) -> impl Fn(&[T], &mut Context) -> Exp {
     move |list, ctx| {
-        let mut result = Exp::Const("Nil".to_string(), vec![]);
-        for item in list.iter().rev() {
-            result = Exp::Const(
-                "Cons".to_string(),
-                vec![element_decompiler(item, ctx), result],
-            );
-        }
-        result
+        // The last items are numbered first.
+        let mut items: Vec<_> = list
+            .iter()
+            .rev()
+            .map(|item| element_decompiler(item, ctx))
+            .collect();
+        items.reverse();
+        items.to_chi()
     }
 }
 
 fn decompile_raw_var(variable: &Variable, context: &mut Context) -> Exp {
     let id = context.get_or_create_variable_id(variable);
-    number_to_exp(id)
+    id.to_chi()
 }
 
 fn decompile_branch(branch: &Branch, context: &mut Context) -> Exp {
     let id = context.get_or_create_constructor_id(&branch.constructor);
-    let id_result = number_to_exp(id);
+    let id_result = id.to_chi();
     let parameters_result = decompile_list(decompile_raw_var)(&branch.parameters, context);
     let exp_result = decompile(branch.expression.as_ref(), context);
     Exp::Const(
@@ -135,7 +146,7 @@ fn decompile_branch(branch: &Branch, context: &mut Context) -> Exp {
 
 fn decompile_var(variable: &Variable, context: &mut Context) -> Exp {
     let id = context.get_or_create_variable_id(variable);
-    Exp::Const("Var".to_string(), vec![number_to_exp(id)])
+    Exp::Const("Var".to_string(), vec![id.to_chi()])
 }
 
 pub fn decompile(exp: &Exp, context: &mut Context) -> Exp {
@@ -143,7 +154,7 @@ pub fn decompile(exp: &Exp, context: &mut Context) -> Exp {
         Exp::Var(variable) => decompile_var(variable, context),
         Exp::Const(constructor, exps) => {
             let id = context.get_or_create_constructor_id(constructor);
-            let id_result = number_to_exp(id);
+            let id_result = id.to_chi();
             let exps_result = decompile_list(decompile)(exps, context);
             Exp::Const("Const".to_string(), vec![id_result, exps_result])
         }
@@ -156,14 +167,14 @@ pub fn decompile(exp: &Exp, context: &mut Context) -> Exp {
             let id = context.get_or_create_variable_id(var);
             Exp::Const(
                 "Lambda".to_string(),
-                vec![number_to_exp(id), decompile(exp, context)],
+                vec![id.to_chi(), decompile(exp, context)],
             )
         }
         Exp::Rec(var, exp) => {
             let id = context.get_or_create_variable_id(var);
             Exp::Const(
                 "Rec".to_string(),
-                vec![number_to_exp(id), decompile(exp, context)],
+                vec![id.to_chi(), decompile(exp, context)],
             )
         }
         Exp::Case(exp, branches) => {
@@ -174,8 +185,77 @@ pub fn decompile(exp: &Exp, context: &mut Context) -> Exp {
     }
 }
 
-static SELF_SUBSTITUTE: LazyLock<Exp> =
-    LazyLock::new(|| concrete::parse(include_str!("subst_expanded.chi")).unwrap());
+fn compile_variable(exp: &Exp, context: &Context) -> Option<Variable> {
+    context
+        .variable
+        .get_by_right(&usize::from_chi(exp).ok()?)
+        .cloned()
+}
+
+fn compile_branch(exp: &Exp, context: &Context) -> Option<Branch> {
+    match exp {
+        Exp::Const(c, arguments) if c == "Branch" && arguments.len() == 3 => Some(Branch {
+            constructor: context
+                .constructor
+                .get_by_right(&usize::from_chi(&arguments[0]).ok()?)?
+                .clone(),
+            parameters: Vec::<Exp>::from_chi(&arguments[1])
+                .ok()?
+                .iter()
+                .map(|parameter| compile_variable(parameter, context))
+                .collect::<Option<_>>()?,
+            expression: Box::new(compile(&arguments[2], context)?),
+        }),
+        _ => None,
+    }
+}
+
+/// The inverse of [`decompile`]: the expression whose standard form `exp` is,
+/// with names looked up in `context`. `None` if `exp` is not a standard form
+/// or uses numbers `context` has no names for.
+pub fn compile(exp: &Exp, context: &Context) -> Option<Exp> {
+    let (c, arguments) = match exp {
+        Exp::Const(c, arguments) => (c.as_str(), arguments.as_slice()),
+        _ => return None,
+    };
+    Some(match (c, arguments) {
+        ("Var", [x]) => Exp::Var(compile_variable(x, context)?),
+        ("Const", [id, arguments]) => Exp::Const(
+            context
+                .constructor
+                .get_by_right(&usize::from_chi(id).ok()?)?
+                .clone(),
+            Vec::<Exp>::from_chi(arguments)
+                .ok()?
+                .iter()
+                .map(|argument| compile(argument, context))
+                .collect::<Option<_>>()?,
+        ),
+        ("Apply", [f, x]) => Exp::Apply(
+            Box::new(compile(f, context)?),
+            Box::new(compile(x, context)?),
+        ),
+        ("Lambda", [x, body]) => Exp::Lambda(
+            compile_variable(x, context)?,
+            Box::new(compile(body, context)?),
+        ),
+        ("Rec", [x, body]) => Exp::Rec(
+            compile_variable(x, context)?,
+            Box::new(compile(body, context)?),
+        ),
+        ("Case", [e, branches]) => Exp::Case(
+            Box::new(compile(e, context)?),
+            Vec::<Exp>::from_chi(branches)
+                .ok()?
+                .iter()
+                .map(|branch| compile_branch(branch, context))
+                .collect::<Option<_>>()?,
+        ),
+        _ => return None,
+    })
+}
+
+static SELF_SUBSTITUTE: LazyLock<Exp> = LazyLock::new(|| include_chi!("subst_expanded.chi"));
 
 pub fn self_substitute(from: &Variable, to: &Exp, exp: &Exp, context: &mut Context) -> Exp {
     let exp_std_form = decompile(exp, context);
@@ -192,19 +272,23 @@ pub fn self_substitute(from: &Variable, to: &Exp, exp: &Exp, context: &mut Conte
     semantic::eval(&apply)
 }
 
-static SELF_INTERPRET: LazyLock<Exp> =
-    LazyLock::new(|| concrete::parse(include_str!("eval_expanded.chi")).unwrap());
+static SELF_INTERPRET: LazyLock<Exp> = LazyLock::new(|| include_chi!("eval_expanded.chi"));
 
-pub fn self_interpret(exp: &Exp, context: &mut Context) -> Exp {
+/// The self-interpreter applied to the standard form of `exp`, which
+/// evaluates to the standard form of the value of `exp`.
+pub fn self_interpretation(exp: &Exp, context: &mut Context) -> Exp {
     let exp_std_form = decompile(exp, context);
-    let apply = Exp::Apply(Box::new(SELF_INTERPRET.clone()), Box::new(exp_std_form));
-    semantic::eval(&apply)
+    Exp::Apply(Box::new(SELF_INTERPRET.clone()), Box::new(exp_std_form))
+}
+
+pub fn self_interpret(exp: &Exp, context: &mut Context) -> Exp {
+    semantic::eval(&self_interpretation(exp, context))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::syntax::concrete;
+    use crate::syntax::{concrete, diff::diff};
 
     #[test]
     fn test_decompile() {
@@ -225,6 +309,29 @@ mod tests {
         assert_eq!(result, "Rec(Suc(Zero()), Var(Suc(Zero())))");
     }
 
+    #[test]
+    fn test_compile() {
+        let term = concrete::parse("rec f = λx. case x of { C(y, z) → f D(y) }").unwrap();
+        let mut context = Context::default();
+        let standard_form = decompile(&term, &mut context);
+        assert_eq!(compile(&standard_form, &context), Some(term.clone()));
+        assert_eq!(
+            compile(&concrete::parse("Var(Suc(Zero()))").unwrap(), &context),
+            Some(Exp::Var("x".to_string()))
+        );
+        assert_eq!(
+            compile(&concrete::parse("Var(Zero(), Zero())").unwrap(), &context),
+            None
+        );
+        assert_eq!(
+            compile(
+                &decompile(&term, &mut Context::default()),
+                &Context::default()
+            ),
+            None
+        );
+    }
+
     #[test]
     fn test_self_substitute() {
         fn test_case(code: &str, from: &str, to: &str, expected_result: &str) {
@@ -256,7 +363,8 @@ mod tests {
             let result = self_interpret(&term, &mut context);
             let expected_result = concrete::parse(expected_result).unwrap();
             let expected_normal_form = decompile(&expected_result, &mut context);
-            assert_eq!(result, expected_normal_form);
+            let differences = diff(&result, &expected_normal_form);
+            assert!(differences.is_empty(), "{}", differences.side_by_side());
         }
 
         test_case("case C(D(),E()) of { C(x, x) → x }", "E()");
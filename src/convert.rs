@@ -0,0 +1,314 @@
+use std::{convert::TryFrom, fmt};
+
+use crate::syntax::{concrete, Constructor, Exp};
+
+/// Rust values as χ values, in the encodings the standard forms use:
+/// naturals are `Zero()` and `Suc(n)`, lists `Nil()` and `Cons(x, xs)`.
+pub trait ToChi {
+    fn to_chi(&self) -> Exp;
+}
+
+/// The inverse of [`ToChi`], for decoding the results of evaluation.
+pub trait FromChi: Sized {
+    fn from_chi(exp: &Exp) -> Result<Self, FromChiError>;
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FromChiError {
+    /// `found` is not one of the constructors a value of type `expected` is
+    /// made with.
+    UnexpectedTerm { expected: String, found: Exp },
+    Arity {
+        constructor: Constructor,
+        expected: usize,
+        found: usize,
+    },
+}
+
+impl fmt::Display for FromChiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FromChiError::UnexpectedTerm { expected, found } => write!(
+                f,
+                "expected a value of type {}, found {}",
+                expected,
+                concrete::format(found)
+            ),
+            FromChiError::Arity {
+                constructor,
+                expected,
+                found,
+            } => write!(
+                f,
+                "expected {} arguments to {}, found {}",
+                expected, constructor, found
+            ),
+        }
+    }
+}
+
+impl std::error::Error for FromChiError {}
+
+impl FromChiError {
+    pub fn unexpected<T: ?Sized>(found: &Exp) -> Self {
+        FromChiError::UnexpectedTerm {
+            expected: std::any::type_name::<T>().to_string(),
+            found: found.clone(),
+        }
+    }
+}
+
+/// The arguments of `exp` if it is `constructor` applied to `arity` of them,
+/// `None` if it is some other term and an error if the arity is wrong.
+pub fn arguments<'a>(
+    exp: &'a Exp,
+    constructor: &str,
+    arity: usize,
+) -> Result<Option<&'a [Exp]>, FromChiError> {
+    match exp {
+        Exp::Const(c, arguments) if c == constructor => {
+            if arguments.len() == arity {
+                Ok(Some(arguments))
+            } else {
+                Err(FromChiError::Arity {
+                    constructor: c.clone(),
+                    expected: arity,
+                    found: arguments.len(),
+                })
+            }
+        }
+        _ => Ok(None),
+    }
+}
+
+fn constant(constructor: &str) -> Exp {
+    Exp::Const(constructor.to_string(), vec![])
+}
+
+impl ToChi for Exp {
+    fn to_chi(&self) -> Exp {
+        self.clone()
+    }
+}
+
+impl FromChi for Exp {
+    fn from_chi(exp: &Exp) -> Result<Self, FromChiError> {
+        Ok(exp.clone())
+    }
+}
+
+impl<T: ToChi + ?Sized> ToChi for &T {
+    fn to_chi(&self) -> Exp {
+        (**self).to_chi()
+    }
+}
+
+impl<T: ToChi + ?Sized> ToChi for Box<T> {
+    fn to_chi(&self) -> Exp {
+        (**self).to_chi()
+    }
+}
+
+impl<T: FromChi> FromChi for Box<T> {
+    fn from_chi(exp: &Exp) -> Result<Self, FromChiError> {
+        T::from_chi(exp).map(Box::new)
+    }
+}
+
+impl ToChi for usize {
+    fn to_chi(&self) -> Exp {
+        (0..*self).fold(constant("Zero"), |n, _| {
+            Exp::Const("Suc".to_string(), vec![n])
+        })
+    }
+}
+
+impl FromChi for usize {
+    fn from_chi(mut exp: &Exp) -> Result<Self, FromChiError> {
+        let mut number = 0;
+        loop {
+            if let Some(n) = arguments(exp, "Suc", 1)? {
+                number += 1;
+                exp = &n[0];
+            } else if arguments(exp, "Zero", 0)?.is_some() {
+                return Ok(number);
+            } else {
+                return Err(FromChiError::unexpected::<usize>(exp));
+            }
+        }
+    }
+}
+
+impl ToChi for bool {
+    fn to_chi(&self) -> Exp {
+        constant(if *self { "True" } else { "False" })
+    }
+}
+
+impl FromChi for bool {
+    fn from_chi(exp: &Exp) -> Result<Self, FromChiError> {
+        if arguments(exp, "True", 0)?.is_some() {
+            Ok(true)
+        } else if arguments(exp, "False", 0)?.is_some() {
+            Ok(false)
+        } else {
+            Err(FromChiError::unexpected::<bool>(exp))
+        }
+    }
+}
+
+impl<T: ToChi> ToChi for Option<T> {
+    fn to_chi(&self) -> Exp {
+        match self {
+            Some(x) => Exp::Const("Some".to_string(), vec![x.to_chi()]),
+            None => constant("None"),
+        }
+    }
+}
+
+impl<T: FromChi> FromChi for Option<T> {
+    fn from_chi(exp: &Exp) -> Result<Self, FromChiError> {
+        if let Some(x) = arguments(exp, "Some", 1)? {
+            Ok(Some(T::from_chi(&x[0])?))
+        } else if arguments(exp, "None", 0)?.is_some() {
+            Ok(None)
+        } else {
+            Err(FromChiError::unexpected::<Self>(exp))
+        }
+    }
+}
+
+impl<T: ToChi> ToChi for [T] {
+    fn to_chi(&self) -> Exp {
+        self.iter().rev().fold(constant("Nil"), |list, x| {
+            Exp::Const("Cons".to_string(), vec![x.to_chi(), list])
+        })
+    }
+}
+
+impl<T: ToChi> ToChi for Vec<T> {
+    fn to_chi(&self) -> Exp {
+        self.as_slice().to_chi()
+    }
+}
+
+impl<T: FromChi> FromChi for Vec<T> {
+    fn from_chi(mut exp: &Exp) -> Result<Self, FromChiError> {
+        let mut items = vec![];
+        loop {
+            if let Some(cons) = arguments(exp, "Cons", 2)? {
+                items.push(T::from_chi(&cons[0])?);
+                exp = &cons[1];
+            } else if arguments(exp, "Nil", 0)?.is_some() {
+                return Ok(items);
+            } else {
+                return Err(FromChiError::unexpected::<Self>(exp));
+            }
+        }
+    }
+}
+
+/// A list of the Unicode code points.
+impl ToChi for str {
+    fn to_chi(&self) -> Exp {
+        self.chars()
+            .map(|c| c as usize)
+            .collect::<Vec<_>>()
+            .to_chi()
+    }
+}
+
+impl ToChi for String {
+    fn to_chi(&self) -> Exp {
+        self.as_str().to_chi()
+    }
+}
+
+impl FromChi for String {
+    fn from_chi(exp: &Exp) -> Result<Self, FromChiError> {
+        Vec::<usize>::from_chi(exp)?
+            .into_iter()
+            .map(|c| u32::try_from(c).ok().and_then(char::from_u32))
+            .collect::<Option<_>>()
+            .ok_or_else(|| FromChiError::unexpected::<String>(exp))
+    }
+}
+
+/// Tuples are `Tuple(…)`, with as many arguments as elements.
+macro_rules! tuple {
+    ($($t:ident $i:tt),*) => {
+        impl<$($t: ToChi),*> ToChi for ($($t,)*) {
+            fn to_chi(&self) -> Exp {
+                Exp::Const("Tuple".to_string(), vec![$(self.$i.to_chi()),*])
+            }
+        }
+
+        impl<$($t: FromChi),*> FromChi for ($($t,)*) {
+            #[allow(unused_variables)]
+            fn from_chi(exp: &Exp) -> Result<Self, FromChiError> {
+                let arity = <[&str]>::len(&[$(stringify!($t)),*]);
+                let elements = arguments(exp, "Tuple", arity)?
+                    .ok_or_else(|| FromChiError::unexpected::<Self>(exp))?;
+                Ok(($($t::from_chi(&elements[$i])?,)*))
+            }
+        }
+    };
+}
+
+tuple!();
+tuple!(A 0);
+tuple!(A 0, B 1);
+tuple!(A 0, B 1, C 2);
+tuple!(A 0, B 1, C 2, D 3);
+tuple!(A 0, B 1, C 2, D 3, E 4);
+tuple!(A 0, B 1, C 2, D 3, E 4, F 5);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::semantic::eval;
+
+    fn round_trip<T: ToChi + FromChi + PartialEq + fmt::Debug>(value: T) {
+        assert_eq!(T::from_chi(&value.to_chi()), Ok(value));
+    }
+
+    #[test]
+    fn test_conversions() {
+        assert_eq!(concrete::format(&2usize.to_chi()), "Suc(Suc(Zero()))");
+        assert_eq!(
+            concrete::format(&(true, vec![Some(0usize)], None::<bool>).to_chi()),
+            "Tuple(True(), Cons(Some(Zero()), Nil()), None())"
+        );
+        round_trip(1000usize);
+        round_trip(vec![(false, 3usize), (true, 0)]);
+        round_trip(Some(Some(vec![()])));
+        round_trip("χ λ".to_string());
+        round_trip((1usize, 2usize, 3usize, 4usize, 5usize, 6usize));
+
+        let add =
+            concrete::parse("rec add = λm. λn. case n of { Zero() → m; Suc(n) → Suc(add m n) }")
+                .unwrap();
+        let sum = Exp::Apply(
+            Box::new(Exp::Apply(Box::new(add), Box::new(3usize.to_chi()))),
+            Box::new(4usize.to_chi()),
+        );
+        assert_eq!(usize::from_chi(&eval(&sum)), Ok(7));
+
+        let pair = concrete::parse("Tuple(True(), Suc(Nil()))").unwrap();
+        assert_eq!(
+            <(bool, usize)>::from_chi(&pair),
+            Err(FromChiError::UnexpectedTerm {
+                expected: "usize".to_string(),
+                found: concrete::parse("Nil()").unwrap()
+            })
+        );
+        assert_eq!(
+            bool::from_chi(&concrete::parse("True(Zero())").unwrap()),
+            Err(FromChiError::Arity {
+                constructor: "True".to_string(),
+                expected: 0,
+                found: 1
+            })
+        );
+    }
+}
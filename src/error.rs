@@ -0,0 +1,129 @@
+use serde::Serialize;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::{prelude::wasm_bindgen, JsValue};
+
+use crate::{
+    lowering::scott::ArityMismatch,
+    semantic::RenameError,
+    syntax::concrete::{self, SyntaxError},
+};
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(typescript_custom_section)]
+const TS_ERROR: &'static str = r#"
+/** What every function of this module which can fail throws. */
+export interface ChiError {
+    kind: "Parse" | "InvalidArgument" | "ArityMismatch" | "Rename";
+    message: string;
+    /** Where in the code a parse error is, in UTF-16 code units. */
+    span?: { start: number; end: number };
+}
+"#;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum ErrorKind {
+    /// The code is neither concrete nor abstract syntax.
+    Parse,
+    /// An argument is not a value of the type expected, such as an expression
+    /// which did not come from `parse` or a snapshot which did not come from
+    /// `Evaluator.snapshot`.
+    InvalidArgument,
+    /// Evaluation matched a constructor against a branch with another number
+    /// of parameters than it has arguments.
+    ArityMismatch,
+    Rename,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// The error thrown to JavaScript, as a plain object, or raised in Python.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Error {
+    pub kind: ErrorKind,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub span: Option<Span>,
+}
+
+/// The number of UTF-16 code units in `code` before the byte `offset`.
+pub(crate) fn utf16_offset(code: &str, offset: usize) -> usize {
+    code[..offset].encode_utf16().count()
+}
+
+impl Error {
+    /// The error for `code`, which failed to parse, pointing where the
+    /// concrete parser gave up.
+    pub fn parse(code: &str) -> Self {
+        let span = match concrete::parse_spanned(code) {
+            Err(SyntaxError { offset }) => {
+                let end = offset + code[offset..].chars().next().map_or(0, char::len_utf8);
+                Some(Span {
+                    start: utf16_offset(code, offset),
+                    end: utf16_offset(code, end),
+                })
+            }
+            Ok(_) => None,
+        };
+        Error {
+            kind: ErrorKind::Parse,
+            message: "not valid concrete or abstract syntax".to_string(),
+            span,
+        }
+    }
+
+    fn new(kind: ErrorKind, message: impl ToString) -> Self {
+        Error {
+            kind,
+            message: message.to_string(),
+            span: None,
+        }
+    }
+}
+
+#[cfg(feature = "wasm")]
+impl From<serde_wasm_bindgen::Error> for Error {
+    fn from(error: serde_wasm_bindgen::Error) -> Self {
+        Error::new(ErrorKind::InvalidArgument, error)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(error: serde_json::Error) -> Self {
+        Error::new(ErrorKind::InvalidArgument, error)
+    }
+}
+
+impl From<ArityMismatch> for Error {
+    fn from(error: ArityMismatch) -> Self {
+        Error::new(ErrorKind::ArityMismatch, error)
+    }
+}
+
+impl From<RenameError> for Error {
+    fn from(error: RenameError) -> Self {
+        Error::new(ErrorKind::Rename, error)
+    }
+}
+
+#[cfg(feature = "wasm")]
+impl From<Error> for JsValue {
+    fn from(error: Error) -> Self {
+        serde_wasm_bindgen::to_value(&error).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_error() {
+        let error = Error::parse("λx. C(x, λ");
+        assert_eq!(error.kind, ErrorKind::Parse);
+        assert_eq!(error.span, Some(Span { start: 9, end: 10 }));
+    }
+}
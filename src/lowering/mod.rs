@@ -0,0 +1,13 @@
+use crate::syntax::{variables, Exp, Variable};
+
+pub mod scott;
+pub mod ski;
+
+/// A variable name starting with `base` which does not occur anywhere in `exp`.
+fn fresh_variable(base: &str, exp: &Exp) -> Variable {
+    let used = variables(exp);
+    (0..)
+        .map(|i| format!("{}{}", base, i))
+        .find(|name| !used.contains(name))
+        .unwrap()
+}
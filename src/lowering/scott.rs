@@ -0,0 +1,317 @@
+use std::fmt;
+
+use crate::{
+    lowering::fresh_variable,
+    syntax::{
+        apply_all, lambda_all, spine,
+        visit::{ExpVisitor, Scope},
+        Branch, Constructor, Exp, Variable,
+    },
+};
+
+/// The constructors a program may build, in a fixed order, with their arities.
+///
+/// Constructor `i` of `k` is encoded as `λc0. … λc(k-1). ci a0 … a(n-1)`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Signature {
+    constructors: Vec<(Constructor, usize)>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ArityMismatch {
+    pub constructor: Constructor,
+    pub expected: usize,
+    pub found: usize,
+}
+
+impl fmt::Display for ArityMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "constructor {} is used with {} and {} arguments",
+            self.constructor, self.expected, self.found
+        )
+    }
+}
+
+impl Signature {
+    pub fn new(constructors: Vec<(Constructor, usize)>) -> Self {
+        Self { constructors }
+    }
+
+    /// Collects the constructors of `exp`, both built and matched on, in
+    /// order of first appearance.
+    pub fn of(exp: &Exp) -> Result<Self, ArityMismatch> {
+        let mut collector = Collector {
+            signature: Self::default(),
+            error: None,
+        };
+        collector.visit(exp);
+        match collector.error {
+            Some(error) => Err(error),
+            None => Ok(collector.signature),
+        }
+    }
+
+    pub fn constructors(&self) -> &[(Constructor, usize)] {
+        &self.constructors
+    }
+
+    fn add(&mut self, constructor: &Constructor, arity: usize) -> Result<(), ArityMismatch> {
+        match self.arity(constructor) {
+            Some(expected) if expected != arity => Err(ArityMismatch {
+                constructor: constructor.clone(),
+                expected,
+                found: arity,
+            }),
+            Some(_) => Ok(()),
+            None => {
+                self.constructors.push((constructor.clone(), arity));
+                Ok(())
+            }
+        }
+    }
+
+    fn index(&self, constructor: &Constructor) -> Option<usize> {
+        self.constructors.iter().position(|(c, _)| c == constructor)
+    }
+
+    fn arity(&self, constructor: &Constructor) -> Option<usize> {
+        self.index(constructor).map(|i| self.constructors[i].1)
+    }
+
+    fn selector_names(&self) -> impl DoubleEndedIterator<Item = Variable> {
+        (0..self.constructors.len()).map(|i| format!("c{}", i))
+    }
+}
+
+/// Adds the constructors it visits to `signature`, stopping at the first one
+/// used with another arity.
+struct Collector {
+    signature: Signature,
+    error: Option<ArityMismatch>,
+}
+
+impl Collector {
+    fn add(&mut self, constructor: &Constructor, arity: usize) {
+        if self.error.is_none() {
+            self.error = self.signature.add(constructor, arity).err();
+        }
+    }
+}
+
+impl ExpVisitor for Collector {
+    fn visit_branch(&mut self, branch: &Branch, scope: &mut Scope) {
+        self.add(&branch.constructor, branch.parameters.len());
+        self.visit_exp(&branch.expression, scope);
+    }
+
+    fn visit_const(&mut self, constructor: &Constructor, arguments: &[Exp], scope: &mut Scope) {
+        self.add(constructor, arguments.len());
+        for argument in arguments {
+            self.visit_exp(argument, scope);
+        }
+    }
+}
+
+/// `Z = λf.(λx.f (λv.x x v)) (λx.f (λv.x x v))`, the call-by-value fixpoint
+/// combinator.
+pub(crate) fn fixpoint() -> Exp {
+    let var = |x: &str| Exp::Var(x.to_string());
+    let half = Exp::Lambda(
+        "x".to_string(),
+        Box::new(Exp::Apply(
+            Box::new(var("f")),
+            Box::new(Exp::Lambda(
+                "v".to_string(),
+                Box::new(apply_all(var("x"), [var("x"), var("v")])),
+            )),
+        )),
+    );
+    Exp::Lambda(
+        "f".to_string(),
+        Box::new(Exp::Apply(Box::new(half.clone()), Box::new(half))),
+    )
+}
+
+struct Encoder<'a> {
+    signature: &'a Signature,
+    // parameter of the thunk around each branch body, so that call-by-value
+    // evaluation does not run every branch when they are passed to a value
+    thunk: Variable,
+    stuck: Variable,
+}
+
+impl Encoder<'_> {
+    fn constructor(&self, constructor: &Constructor, exps: &[Exp]) -> Exp {
+        let index = self
+            .signature
+            .index(constructor)
+            .unwrap_or_else(|| panic!("constructor {} is not in the signature", constructor));
+        let arguments: Vec<_> = (0..exps.len()).map(|i| format!("a{}", i)).collect();
+        let selectors: Vec<_> = self.signature.selector_names().collect();
+        let body = apply_all(
+            Exp::Var(selectors[index].clone()),
+            arguments.iter().cloned().map(Exp::Var),
+        );
+        let value = lambda_all(
+            arguments.into_iter(),
+            lambda_all(selectors.into_iter(), body),
+        );
+        apply_all(value, exps.iter().map(|exp| self.encode(exp)))
+    }
+
+    fn branch(&self, constructor: &Constructor, arity: usize, branches: &[Branch]) -> Exp {
+        match branches.iter().find(|b| &b.constructor == constructor) {
+            Some(branch) => lambda_all(
+                branch.parameters.iter().cloned(),
+                Exp::Lambda(
+                    self.thunk.clone(),
+                    Box::new(self.encode(&branch.expression)),
+                ),
+            ),
+            None => lambda_all(
+                (0..=arity).map(|i| format!("a{}", i)),
+                Exp::Var(self.stuck.clone()),
+            ),
+        }
+    }
+
+    fn encode(&self, exp: &Exp) -> Exp {
+        match exp {
+            Exp::Var(x) => Exp::Var(x.clone()),
+            Exp::Lambda(x, exp) => Exp::Lambda(x.clone(), Box::new(self.encode(exp))),
+            Exp::Apply(f, x) => Exp::Apply(Box::new(self.encode(f)), Box::new(self.encode(x))),
+            Exp::Rec(x, exp) => Exp::Apply(
+                Box::new(fixpoint()),
+                Box::new(Exp::Lambda(x.clone(), Box::new(self.encode(exp)))),
+            ),
+            Exp::Const(constructor, exps) => self.constructor(constructor, exps),
+            Exp::Case(exp, branches) => {
+                let handlers = self
+                    .signature
+                    .constructors
+                    .iter()
+                    .map(|(constructor, arity)| self.branch(constructor, *arity, branches));
+                let selected = apply_all(self.encode(exp), handlers);
+                let identity = Exp::Lambda("x".to_string(), Box::new(Exp::Var("x".to_string())));
+                Exp::Apply(Box::new(selected), Box::new(identity))
+            }
+        }
+    }
+}
+
+/// Translates a closed χ program into the pure λ-calculus: constructors and
+/// `case` use the Scott encoding over `signature`, and `rec` uses the
+/// call-by-value fixpoint combinator. A `case` without a matching branch
+/// evaluates to a free variable instead of getting stuck.
+pub fn encode(exp: &Exp, signature: &Signature) -> Exp {
+    let encoder = Encoder {
+        signature,
+        thunk: fresh_variable("u", exp),
+        stuck: fresh_variable("stuck", exp),
+    };
+    encoder.encode(exp)
+}
+
+/// Reads back a Scott-encoded constructor value, as produced by evaluating an
+/// encoded program with [`crate::semantic::eval`].
+pub fn decode(value: &Exp, signature: &Signature) -> Option<Exp> {
+    let mut body = value;
+    let mut selectors = vec![];
+    for _ in 0..signature.constructors.len() {
+        let Exp::Lambda(x, inner) = body else {
+            return None;
+        };
+        selectors.push(x);
+        body = inner;
+    }
+    let (head, arguments) = spine(body);
+    let Exp::Var(head) = head else {
+        return None;
+    };
+    // the last binder with this name is the one in scope
+    let index = selectors.iter().rposition(|&x| x == head)?;
+    let (constructor, arity) = &signature.constructors[index];
+    if arguments.len() != *arity {
+        return None;
+    }
+    let arguments = arguments
+        .into_iter()
+        .map(|argument| decode(argument, signature))
+        .collect::<Option<_>>()?;
+    Some(Exp::Const(constructor.clone(), arguments))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{semantic::eval, syntax::concrete};
+
+    fn assert_same_value(code: &str) {
+        let program = concrete::parse(code).unwrap();
+        let signature = Signature::of(&program).unwrap();
+        let encoded = encode(&program, &signature);
+        let decoded = decode(&eval(&encoded), &signature).unwrap();
+        assert_eq!(decoded, eval(&program));
+    }
+
+    #[test]
+    fn test_signature() {
+        let program = concrete::parse("case C(D()) of { E(x, y) → C(x) }").unwrap();
+        let signature = Signature::of(&program).unwrap();
+        assert_eq!(
+            signature.constructors(),
+            &[
+                ("C".to_string(), 1),
+                ("D".to_string(), 0),
+                ("E".to_string(), 2)
+            ]
+        );
+
+        let program = concrete::parse("case C(D()) of { C() → C() }").unwrap();
+        assert_eq!(
+            Signature::of(&program),
+            Err(ArityMismatch {
+                constructor: "C".to_string(),
+                expected: 1,
+                found: 0
+            })
+        );
+    }
+
+    #[test]
+    fn test_encode() {
+        assert_same_value("Suc(Suc(Zero()))");
+        assert_same_value("case C(D(),E()) of { C(x, x) → x }");
+        assert_same_value("case C(λx.x, Zero()) of { C(f, x) → f x }");
+        assert_same_value("case (λx.x) C() of { C() → C() }");
+        assert_same_value(
+            "(rec add = λm.λn. case n of {
+                Zero() → m;
+                Suc(n) → Suc(add m n)
+            }) Suc(Suc(Zero())) Suc(Zero())",
+        );
+        assert_same_value(
+            "(rec map = λf.λxs. case xs of {
+                Nil() → Nil();
+                Cons(x, xs) → Cons(f x, map f xs)
+            }) (λx. Suc(x)) Cons(Zero(), Cons(Suc(Zero()), Nil()))",
+        );
+    }
+
+    #[test]
+    fn test_encode_is_pure() {
+        fn is_pure(exp: &Exp) -> bool {
+            match exp {
+                Exp::Var(_) => true,
+                Exp::Lambda(_, exp) => is_pure(exp),
+                Exp::Apply(f, x) => is_pure(f) && is_pure(x),
+                _ => false,
+            }
+        }
+        let program = concrete::parse(include_str!("../bootstrapping/equal.chi")).unwrap();
+        let signature = Signature::of(&program).unwrap();
+        assert!(is_pure(&encode(&program, &signature)));
+    }
+}
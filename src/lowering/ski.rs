@@ -0,0 +1,154 @@
+use crate::{
+    lowering::scott::Signature,
+    syntax::{apply_all, spine, Exp, Variable},
+};
+
+fn combinator(name: &str) -> Exp {
+    Exp::Const(name.to_string(), vec![])
+}
+
+fn occurs_free(x: &str, exp: &Exp) -> bool {
+    match exp {
+        Exp::Var(y) => x == y,
+        Exp::Apply(f, y) => occurs_free(x, f) || occurs_free(x, y),
+        _ => false,
+    }
+}
+
+/// Bracket abstraction `[x] exp` over a term which is already combinator code.
+fn abstract_variable(x: &Variable, exp: &Exp) -> Exp {
+    match exp {
+        Exp::Var(y) if x == y => combinator("I"),
+        _ if !occurs_free(x, exp) => Exp::Apply(Box::new(combinator("K")), Box::new(exp.clone())),
+        Exp::Apply(f, y) if matches!(y.as_ref(), Exp::Var(y) if x == y) && !occurs_free(x, f) => {
+            f.as_ref().clone()
+        }
+        Exp::Apply(f, y) => apply_all(
+            combinator("S"),
+            [abstract_variable(x, f), abstract_variable(x, y)],
+        ),
+        _ => unreachable!("combinator code only contains applications and variables"),
+    }
+}
+
+/// Compiles a pure λ-term, such as the output of [`super::scott::encode`], to
+/// S, K and I combinators, represented as nullary constructors. Other nullary
+/// constructors are kept as constants.
+pub fn compile(exp: &Exp) -> Exp {
+    match exp {
+        Exp::Var(x) => Exp::Var(x.clone()),
+        Exp::Apply(f, x) => Exp::Apply(Box::new(compile(f)), Box::new(compile(x))),
+        Exp::Lambda(x, exp) => abstract_variable(x, &compile(exp)),
+        Exp::Const(_, exps) if exps.is_empty() => exp.clone(),
+        _ => panic!("only pure λ-terms can be compiled to combinators"),
+    }
+}
+
+/// Reduces a combinator term to weak head normal form in normal order, treating
+/// variables as opaque atoms. Returns `None` if it takes more than
+/// `step_limit` steps.
+pub fn reduce(exp: &Exp, step_limit: usize) -> Option<Exp> {
+    let mut steps = step_limit;
+    reduce_with(exp.clone(), &mut steps)
+}
+
+fn reduce_with(exp: Exp, steps: &mut usize) -> Option<Exp> {
+    // arguments of the current head, the next one on top
+    let mut arguments = vec![];
+    let mut head = exp;
+    loop {
+        match head {
+            Exp::Apply(f, x) => {
+                arguments.push(*x);
+                head = *f;
+                continue;
+            }
+            Exp::Const(ref c, _) if c == "I" && !arguments.is_empty() => {
+                head = arguments.pop().unwrap();
+            }
+            Exp::Const(ref c, _) if c == "K" && arguments.len() >= 2 => {
+                head = arguments.pop().unwrap();
+                arguments.pop();
+            }
+            Exp::Const(ref c, _) if c == "S" && arguments.len() >= 3 => {
+                let f = arguments.pop().unwrap();
+                let g = arguments.pop().unwrap();
+                let x = arguments.pop().unwrap();
+                arguments.push(Exp::Apply(Box::new(g), Box::new(x.clone())));
+                arguments.push(x);
+                head = f;
+            }
+            _ => return Some(apply_all(head, arguments.into_iter().rev())),
+        }
+        *steps = steps.checked_sub(1)?;
+    }
+}
+
+fn decode_with(exp: &Exp, signature: &Signature, steps: &mut usize) -> Option<Exp> {
+    let constructors = signature.constructors();
+    let selectors = (0..constructors.len()).map(|i| Exp::Var(format!("c{}", i)));
+    let selected = reduce_with(apply_all(exp.clone(), selectors), steps)?;
+    let (head, arguments) = spine(&selected);
+    let Exp::Var(head) = head else {
+        return None;
+    };
+    let (constructor, arity) = &constructors[head[1..].parse::<usize>().ok()?];
+    if arguments.len() != *arity {
+        return None;
+    }
+    let arguments = arguments
+        .into_iter()
+        .map(|argument| decode_with(argument, signature, steps))
+        .collect::<Option<_>>()?;
+    Some(Exp::Const(constructor.clone(), arguments))
+}
+
+/// Reads back the Scott-encoded constructor value computed by a closed
+/// combinator term, by applying it to atoms standing for each constructor.
+pub fn decode(exp: &Exp, signature: &Signature, step_limit: usize) -> Option<Exp> {
+    let mut steps = step_limit;
+    decode_with(exp, signature, &mut steps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lowering::scott, semantic::eval, syntax::concrete};
+
+    #[test]
+    fn test_compile() {
+        let term = concrete::parse("λx.λy.x").unwrap();
+        assert_eq!(concrete::format(&compile(&term)), "K()");
+        let term = concrete::parse("λx.λy.y x").unwrap();
+        assert_eq!(concrete::format(&compile(&term)), "S() (K() (S() I())) K()");
+    }
+
+    #[test]
+    fn test_reduce() {
+        let term = concrete::parse("S() K() K() x").unwrap();
+        assert_eq!(reduce(&term, 10), Some(Exp::Var("x".to_string())));
+        let omega = concrete::parse("S() I() I() (S() I() I())").unwrap();
+        assert_eq!(reduce(&omega, 1000), None);
+    }
+
+    #[test]
+    fn test_same_value() {
+        fn test_case(code: &str) {
+            let program = concrete::parse(code).unwrap();
+            let signature = scott::Signature::of(&program).unwrap();
+            let compiled = compile(&scott::encode(&program, &signature));
+            let decoded = decode(&compiled, &signature, 1_000_000).unwrap();
+            assert_eq!(decoded, eval(&program));
+        }
+
+        test_case("Suc(Suc(Zero()))");
+        test_case("case C(D(),E()) of { C(x, x) → x }");
+        test_case("case C(λx.x, Zero()) of { C(f, x) → f x }");
+        test_case(
+            "(rec add = λm.λn. case n of {
+                Zero() → m;
+                Suc(n) → Suc(add m n)
+            }) Suc(Suc(Zero())) Suc(Zero())",
+        );
+    }
+}
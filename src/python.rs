@@ -0,0 +1,273 @@
+use pyo3::{
+    create_exception,
+    exceptions::{PyException, PyTypeError},
+    prelude::*,
+    types::{PyList, PySequence, PyTuple},
+};
+
+use crate::{
+    bootstrapping::{self, Context},
+    error::{Error, ErrorKind},
+    semantic::{self, Evaluator},
+    syntax::{self, abst, concrete, Branch, Exp},
+};
+
+create_exception!(
+    chi,
+    ChiError,
+    PyException,
+    "Raised with the kind of error and a message, as `ChiError` is thrown to JavaScript."
+);
+
+impl From<Error> for PyErr {
+    fn from(error: Error) -> Self {
+        ChiError::new_err((format!("{:?}", error.kind), error.message))
+    }
+}
+
+/// An expression as nested tuples, tagged like the JSON of [`Exp`]:
+/// `("Var", x)`, `("Apply", f, x)`, `("Lambda", x, body)`, `("Rec", x, body)`,
+/// `("Case", e, [(constructor, [parameter, …], body), …])` and
+/// `("Const", constructor, [argument, …])`.
+struct PyExp(Exp);
+
+fn items<'py, T: FromPyObject<'py>>(sequence: &Bound<'py, PyAny>) -> PyResult<Vec<T>> {
+    sequence
+        .downcast::<PySequence>()?
+        .try_iter()?
+        .map(|item| item?.extract())
+        .collect()
+}
+
+fn boxed(exp: Bound<'_, PyAny>) -> PyResult<Box<Exp>> {
+    Ok(Box::new(exp.extract::<PyExp>()?.0))
+}
+
+impl<'py> FromPyObject<'py> for PyExp {
+    fn extract_bound(exp: &Bound<'py, PyAny>) -> PyResult<Self> {
+        let exp = exp.downcast::<PyTuple>()?;
+        let tag: String = exp.get_item(0)?.extract()?;
+        let field = |i| exp.get_item(i);
+        Ok(PyExp(match (tag.as_str(), exp.len()) {
+            ("Var", 2) => Exp::Var(field(1)?.extract()?),
+            ("Apply", 3) => Exp::Apply(boxed(field(1)?)?, boxed(field(2)?)?),
+            ("Lambda", 3) => Exp::Lambda(field(1)?.extract()?, boxed(field(2)?)?),
+            ("Rec", 3) => Exp::Rec(field(1)?.extract()?, boxed(field(2)?)?),
+            ("Case", 3) => Exp::Case(
+                boxed(field(1)?)?,
+                items::<Bound<PyAny>>(&field(2)?)?
+                    .into_iter()
+                    .map(|branch| {
+                        let (constructor, parameters, expression): (_, Bound<PyAny>, _) =
+                            branch.extract()?;
+                        Ok(Branch {
+                            constructor,
+                            parameters: items(&parameters)?,
+                            expression: boxed(expression)?,
+                        })
+                    })
+                    .collect::<PyResult<_>>()?,
+            ),
+            ("Const", 3) => Exp::Const(
+                field(1)?.extract()?,
+                items::<PyExp>(&field(2)?)?
+                    .into_iter()
+                    .map(|argument| argument.0)
+                    .collect(),
+            ),
+            _ => {
+                return Err(PyTypeError::new_err(format!(
+                    "not an expression: {}",
+                    exp.repr()?
+                )))
+            }
+        }))
+    }
+}
+
+impl<'py> IntoPyObject<'py> for PyExp {
+    type Target = PyTuple;
+    type Output = Bound<'py, PyTuple>;
+    type Error = PyErr;
+
+    fn into_pyobject(self, py: Python<'py>) -> PyResult<Bound<'py, PyTuple>> {
+        let exp = |exp: Exp| PyExp(exp).into_pyobject(py);
+        match self.0 {
+            Exp::Var(x) => ("Var", x).into_pyobject(py),
+            Exp::Apply(f, x) => ("Apply", exp(*f)?, exp(*x)?).into_pyobject(py),
+            Exp::Lambda(x, body) => ("Lambda", x, exp(*body)?).into_pyobject(py),
+            Exp::Rec(x, body) => ("Rec", x, exp(*body)?).into_pyobject(py),
+            Exp::Case(e, branches) => {
+                let branches = branches
+                    .into_iter()
+                    .map(|branch| {
+                        (
+                            branch.constructor,
+                            PyList::new(py, branch.parameters)?,
+                            exp(*branch.expression)?,
+                        )
+                            .into_pyobject(py)
+                    })
+                    .collect::<PyResult<Vec<_>>>()?;
+                ("Case", exp(*e)?, PyList::new(py, branches)?).into_pyobject(py)
+            }
+            Exp::Const(constructor, arguments) => {
+                let arguments = arguments
+                    .into_iter()
+                    .map(exp)
+                    .collect::<PyResult<Vec<_>>>()?;
+                ("Const", constructor, PyList::new(py, arguments)?).into_pyobject(py)
+            }
+        }
+    }
+}
+
+/// The numbering of variables and constructors in standard forms.
+#[pyclass(name = "Context")]
+#[derive(Default)]
+struct PyContext(Context);
+
+#[pymethods]
+impl PyContext {
+    #[new]
+    fn new() -> Self {
+        Self::default()
+    }
+
+    fn set_variable(&mut self, variable: String, id: usize) {
+        self.0.set_variable(variable, id)
+    }
+
+    fn set_constructor(&mut self, constructor: String, id: usize) {
+        self.0.set_constructor(constructor, id)
+    }
+
+    fn variables(&self) -> Vec<(String, usize)> {
+        self.0.variables()
+    }
+
+    fn constructors(&self) -> Vec<(String, usize)> {
+        self.0.constructors()
+    }
+}
+
+/// Runs `f` with `context`, or with a new context if there is none.
+fn with_context<T>(context: Option<PyRefMut<PyContext>>, f: impl FnOnce(&mut Context) -> T) -> T {
+    match context {
+        Some(mut context) => f(&mut context.0),
+        None => f(&mut Context::default()),
+    }
+}
+
+#[pyfunction]
+fn parse(code: &str) -> PyResult<PyExp> {
+    let (exp, _) = syntax::parse(code).map_err(|_| Error::parse(code))?;
+    Ok(PyExp(exp))
+}
+
+#[pyfunction]
+fn format_concrete(exp: PyExp) -> String {
+    concrete::format(&exp.0)
+}
+
+#[pyfunction]
+fn format_abstract(exp: PyExp) -> String {
+    abst::format(&exp.0)
+}
+
+/// The value of `exp`, or `None` if it takes more than `limit` steps. Raises
+/// `ChiError` if a value is matched against a branch with another number of
+/// parameters.
+#[pyfunction]
+#[pyo3(signature = (exp, limit = None))]
+fn eval(py: Python<'_>, exp: PyExp, limit: Option<usize>) -> PyResult<Option<PyExp>> {
+    run(py, &exp.0, limit)
+}
+
+fn run(py: Python<'_>, exp: &Exp, limit: Option<usize>) -> PyResult<Option<PyExp>> {
+    let mut evaluator = Evaluator::start(exp);
+    py.allow_threads(|| evaluator.try_run(limit.unwrap_or(usize::MAX)))
+        .map_err(Error::from)?;
+    Ok(evaluator.result().cloned().map(PyExp))
+}
+
+#[pyfunction]
+fn substitute(exp: PyExp, variable: &str, to: PyExp) -> PyExp {
+    PyExp(semantic::substitute(&exp.0, variable, &to.0))
+}
+
+/// The standard form of `exp`, numbering new names in `context`.
+#[pyfunction]
+#[pyo3(signature = (exp, context = None))]
+fn decompile(exp: PyExp, context: Option<PyRefMut<PyContext>>) -> PyExp {
+    PyExp(with_context(context, |context| {
+        bootstrapping::decompile(&exp.0, context)
+    }))
+}
+
+/// The expression whose standard form `exp` is.
+#[pyfunction]
+fn compile(exp: PyExp, context: PyRef<PyContext>) -> PyResult<PyExp> {
+    let exp = bootstrapping::compile(&exp.0, &context.0).ok_or_else(|| Error {
+        kind: ErrorKind::InvalidArgument,
+        message: "not a standard form with names in the context".to_string(),
+        span: None,
+    })?;
+    Ok(PyExp(exp))
+}
+
+/// The standard form of the value of `exp`, evaluated by the
+/// self-interpreter, or `None` if that takes more than `limit` steps. Raises
+/// `ChiError` as `eval` does.
+#[pyfunction]
+#[pyo3(signature = (exp, context = None, limit = None))]
+fn self_interpret(
+    py: Python<'_>,
+    exp: PyExp,
+    context: Option<PyRefMut<PyContext>>,
+    limit: Option<usize>,
+) -> PyResult<Option<PyExp>> {
+    let interpretation = with_context(context, |context| {
+        bootstrapping::self_interpretation(&exp.0, context)
+    });
+    run(py, &interpretation, limit)
+}
+
+#[pymodule]
+fn chi(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add("ChiError", module.py().get_type::<ChiError>())?;
+    module.add_class::<PyContext>()?;
+    module.add_function(wrap_pyfunction!(parse, module)?)?;
+    module.add_function(wrap_pyfunction!(format_concrete, module)?)?;
+    module.add_function(wrap_pyfunction!(format_abstract, module)?)?;
+    module.add_function(wrap_pyfunction!(eval, module)?)?;
+    module.add_function(wrap_pyfunction!(substitute, module)?)?;
+    module.add_function(wrap_pyfunction!(decompile, module)?)?;
+    module.add_function(wrap_pyfunction!(compile, module)?)?;
+    module.add_function(wrap_pyfunction!(self_interpret, module)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exp_conversion() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let exp = concrete::parse("rec f = λx. case x of { C(y, z) → f D(y, λw. w) }").unwrap();
+            let tuple = PyExp(exp.clone()).into_pyobject(py).unwrap();
+            assert_eq!(
+                tuple.get_item(0).unwrap().extract::<String>().unwrap(),
+                "Rec"
+            );
+            assert_eq!(tuple.extract::<PyExp>().unwrap().0, exp);
+
+            let var = ("Var", "x").into_pyobject(py).unwrap();
+            assert_eq!(var.extract::<PyExp>().unwrap().0, Exp::Var("x".to_string()));
+            let bad = ("Var", "x", "y").into_pyobject(py).unwrap();
+            assert!(bad.extract::<PyExp>().is_err());
+        });
+    }
+}
@@ -0,0 +1,539 @@
+//! Patterns over expressions and rewriting with rules made of them.
+//!
+//! Patterns are written in concrete syntax, where `?a` stands for any subterm,
+//! or for any name in the place of a binder. A metavariable used more than
+//! once has to stand for the same thing everywhere: in `λ?x. ?x` the body has
+//! to be the variable the λ binds. Matching is purely syntactic, so binders
+//! have to be named alike to match and rewriting does not rename them.
+
+use std::{collections::BTreeMap, fmt};
+
+use crate::syntax::{
+    concrete::{self, SyntaxError},
+    visit::{walk_fold, ExpFolder, Scope},
+    Branch, Constructor, Exp, Path, Variable,
+};
+
+/// What a concrete-syntax pattern writes `?` as, so that it parses as a
+/// variable.
+const META: &str = "__meta_";
+
+/// The name of a binder in a pattern.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Name {
+    Exact(Variable),
+    /// Any name, bound to the metavariable as a variable.
+    Meta(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Pattern {
+    /// Any subterm.
+    Meta(String),
+    Var(Variable),
+    Apply(Box<Pattern>, Box<Pattern>),
+    Lambda(Name, Box<Pattern>),
+    Case(Box<Pattern>, Vec<BranchPattern>),
+    Rec(Name, Box<Pattern>),
+    Const(Constructor, Vec<Pattern>),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BranchPattern {
+    pub constructor: Constructor,
+    pub parameters: Vec<Name>,
+    pub expression: Pattern,
+}
+
+/// What the metavariables of a pattern stand for in a match.
+pub type Bindings = BTreeMap<String, Exp>;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PatternError {
+    Syntax(SyntaxError),
+    /// A rule is not of the form `pattern => pattern`.
+    NoArrow,
+    /// The right-hand side of a rule uses a metavariable its left-hand side
+    /// does not bind.
+    Unbound(String),
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatternError::Syntax(SyntaxError { offset }) => {
+                write!(f, "invalid pattern at byte {}", offset)
+            }
+            PatternError::NoArrow => write!(f, "a rule is written `pattern => pattern`"),
+            PatternError::Unbound(meta) => {
+                write!(f, "?{} is not bound by the left-hand side", meta)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PatternError {}
+
+impl From<SyntaxError> for PatternError {
+    fn from(error: SyntaxError) -> Self {
+        PatternError::Syntax(error)
+    }
+}
+
+fn name(x: &str) -> Name {
+    match x.strip_prefix(META) {
+        Some(meta) => Name::Meta(meta.to_string()),
+        None => Name::Exact(x.to_string()),
+    }
+}
+
+fn bind(bindings: &mut Bindings, meta: &str, exp: &Exp) -> bool {
+    match bindings.get(meta) {
+        Some(bound) => bound == exp,
+        None => {
+            bindings.insert(meta.to_string(), exp.clone());
+            true
+        }
+    }
+}
+
+impl Name {
+    fn matches(&self, x: &Variable, bindings: &mut Bindings) -> bool {
+        match self {
+            Name::Exact(name) => name == x,
+            Name::Meta(meta) => bind(bindings, meta, &Exp::Var(x.clone())),
+        }
+    }
+
+    fn instantiate(&self, bindings: &Bindings) -> Option<Variable> {
+        match self {
+            Name::Exact(name) => Some(name.clone()),
+            Name::Meta(meta) => match bindings.get(meta)? {
+                Exp::Var(x) => Some(x.clone()),
+                _ => None,
+            },
+        }
+    }
+
+    fn to_exp_name(&self) -> Variable {
+        match self {
+            Name::Exact(name) => name.clone(),
+            Name::Meta(meta) => format!("{}{}", META, meta),
+        }
+    }
+}
+
+impl Pattern {
+    /// Parses a pattern in concrete syntax, with `?a` for metavariables.
+    pub fn parse(code: &str) -> Result<Self, PatternError> {
+        let lead = code.len() - code.trim_start().len();
+        // Every `?` grows by the length of the prefix less one.
+        let mut metas = vec![];
+        let mut escaped = String::with_capacity(code.len());
+        for (offset, c) in code[lead..].char_indices() {
+            if c == '?' {
+                metas.push(offset);
+                escaped.push_str(META);
+            } else {
+                escaped.push(c);
+            }
+        }
+        let (exp, _) = concrete::parse_spanned(&escaped).map_err(|SyntaxError { offset }| {
+            let before = metas
+                .iter()
+                .enumerate()
+                .take_while(|(i, &meta)| meta + i * (META.len() - 1) < offset)
+                .count();
+            SyntaxError {
+                offset: lead + offset - before * (META.len() - 1),
+            }
+        })?;
+        Ok(Self::from_exp(&exp))
+    }
+
+    /// The pattern written as `exp`, in which variables and binders named
+    /// with the prefix `__meta_` are metavariables.
+    fn from_exp(exp: &Exp) -> Self {
+        match exp {
+            Exp::Var(x) => match x.strip_prefix(META) {
+                Some(meta) => Pattern::Meta(meta.to_string()),
+                None => Pattern::Var(x.clone()),
+            },
+            Exp::Apply(f, x) => {
+                Pattern::Apply(Box::new(Self::from_exp(f)), Box::new(Self::from_exp(x)))
+            }
+            Exp::Lambda(x, body) => Pattern::Lambda(name(x), Box::new(Self::from_exp(body))),
+            Exp::Rec(x, body) => Pattern::Rec(name(x), Box::new(Self::from_exp(body))),
+            Exp::Case(e, branches) => Pattern::Case(
+                Box::new(Self::from_exp(e)),
+                branches
+                    .iter()
+                    .map(|branch| BranchPattern {
+                        constructor: branch.constructor.clone(),
+                        parameters: branch.parameters.iter().map(|x| name(x)).collect(),
+                        expression: Self::from_exp(&branch.expression),
+                    })
+                    .collect(),
+            ),
+            Exp::Const(constructor, arguments) => Pattern::Const(
+                constructor.clone(),
+                arguments.iter().map(Self::from_exp).collect(),
+            ),
+        }
+    }
+
+    /// The inverse of `from_exp`.
+    fn to_exp(&self) -> Exp {
+        match self {
+            Pattern::Meta(meta) => Exp::Var(format!("{}{}", META, meta)),
+            Pattern::Var(x) => Exp::Var(x.clone()),
+            Pattern::Apply(f, x) => Exp::Apply(Box::new(f.to_exp()), Box::new(x.to_exp())),
+            Pattern::Lambda(x, body) => Exp::Lambda(x.to_exp_name(), Box::new(body.to_exp())),
+            Pattern::Rec(x, body) => Exp::Rec(x.to_exp_name(), Box::new(body.to_exp())),
+            Pattern::Case(e, branches) => Exp::Case(
+                Box::new(e.to_exp()),
+                branches
+                    .iter()
+                    .map(|branch| Branch {
+                        constructor: branch.constructor.clone(),
+                        parameters: branch.parameters.iter().map(Name::to_exp_name).collect(),
+                        expression: Box::new(branch.expression.to_exp()),
+                    })
+                    .collect(),
+            ),
+            Pattern::Const(constructor, arguments) => Exp::Const(
+                constructor.clone(),
+                arguments.iter().map(Pattern::to_exp).collect(),
+            ),
+        }
+    }
+
+    /// What the metavariables stand for if `exp` matches.
+    pub fn matches(&self, exp: &Exp) -> Option<Bindings> {
+        let mut bindings = Bindings::new();
+        if self.match_into(exp, &mut bindings) {
+            Some(bindings)
+        } else {
+            None
+        }
+    }
+
+    fn match_into(&self, exp: &Exp, bindings: &mut Bindings) -> bool {
+        match (self, exp) {
+            (Pattern::Meta(meta), _) => bind(bindings, meta, exp),
+            (Pattern::Var(x), Exp::Var(y)) => x == y,
+            (Pattern::Apply(p, q), Exp::Apply(f, x)) => {
+                p.match_into(f, bindings) && q.match_into(x, bindings)
+            }
+            (Pattern::Lambda(x, p), Exp::Lambda(y, body))
+            | (Pattern::Rec(x, p), Exp::Rec(y, body)) => {
+                x.matches(y, bindings) && p.match_into(body, bindings)
+            }
+            (Pattern::Case(p, patterns), Exp::Case(e, branches)) => {
+                p.match_into(e, bindings)
+                    && patterns.len() == branches.len()
+                    && patterns.iter().zip(branches).all(|(pattern, branch)| {
+                        pattern.constructor == branch.constructor
+                            && pattern.parameters.len() == branch.parameters.len()
+                            && Iterator::zip(pattern.parameters.iter(), &branch.parameters)
+                                .all(|(x, y)| x.matches(y, bindings))
+                            && pattern.expression.match_into(&branch.expression, bindings)
+                    })
+            }
+            (Pattern::Const(c, patterns), Exp::Const(d, arguments)) => {
+                c == d
+                    && patterns.len() == arguments.len()
+                    && Iterator::zip(patterns.iter(), arguments)
+                        .all(|(pattern, argument)| pattern.match_into(argument, bindings))
+            }
+            _ => false,
+        }
+    }
+
+    /// The expression with the metavariables replaced by what they are bound
+    /// to, or `None` if one is unbound or, in the place of a binder, bound to
+    /// something other than a variable.
+    pub fn instantiate(&self, bindings: &Bindings) -> Option<Exp> {
+        Some(match self {
+            Pattern::Meta(meta) => bindings.get(meta)?.clone(),
+            Pattern::Var(x) => Exp::Var(x.clone()),
+            Pattern::Apply(f, x) => Exp::Apply(
+                Box::new(f.instantiate(bindings)?),
+                Box::new(x.instantiate(bindings)?),
+            ),
+            Pattern::Lambda(x, body) => Exp::Lambda(
+                x.instantiate(bindings)?,
+                Box::new(body.instantiate(bindings)?),
+            ),
+            Pattern::Rec(x, body) => Exp::Rec(
+                x.instantiate(bindings)?,
+                Box::new(body.instantiate(bindings)?),
+            ),
+            Pattern::Case(e, branches) => Exp::Case(
+                Box::new(e.instantiate(bindings)?),
+                branches
+                    .iter()
+                    .map(|branch| {
+                        Some(Branch {
+                            constructor: branch.constructor.clone(),
+                            parameters: branch
+                                .parameters
+                                .iter()
+                                .map(|x| x.instantiate(bindings))
+                                .collect::<Option<_>>()?,
+                            expression: Box::new(branch.expression.instantiate(bindings)?),
+                        })
+                    })
+                    .collect::<Option<_>>()?,
+            ),
+            Pattern::Const(constructor, arguments) => Exp::Const(
+                constructor.clone(),
+                arguments
+                    .iter()
+                    .map(|argument| argument.instantiate(bindings))
+                    .collect::<Option<_>>()?,
+            ),
+        })
+    }
+
+    /// The metavariables of the pattern, each once.
+    pub fn metavariables(&self) -> Vec<&str> {
+        let mut metas = vec![];
+        self.collect_metavariables(&mut metas);
+        metas
+    }
+
+    fn collect_metavariables<'a>(&'a self, metas: &mut Vec<&'a str>) {
+        fn add<'a>(metas: &mut Vec<&'a str>, meta: &'a str) {
+            if !metas.contains(&meta) {
+                metas.push(meta);
+            }
+        }
+        fn add_name<'a>(metas: &mut Vec<&'a str>, name: &'a Name) {
+            if let Name::Meta(meta) = name {
+                add(metas, meta);
+            }
+        }
+        match self {
+            Pattern::Meta(meta) => add(metas, meta),
+            Pattern::Var(_) => {}
+            Pattern::Apply(f, x) => {
+                f.collect_metavariables(metas);
+                x.collect_metavariables(metas);
+            }
+            Pattern::Lambda(x, body) | Pattern::Rec(x, body) => {
+                add_name(metas, x);
+                body.collect_metavariables(metas);
+            }
+            Pattern::Case(e, branches) => {
+                e.collect_metavariables(metas);
+                for branch in branches {
+                    for parameter in &branch.parameters {
+                        add_name(metas, parameter);
+                    }
+                    branch.expression.collect_metavariables(metas);
+                }
+            }
+            Pattern::Const(_, arguments) => {
+                for argument in arguments {
+                    argument.collect_metavariables(metas);
+                }
+            }
+        }
+    }
+
+    /// Every subterm of `exp` which matches, with its path, outermost first.
+    pub fn find(&self, exp: &Exp) -> Vec<(Path, Bindings)> {
+        exp.subterms()
+            .into_iter()
+            .filter_map(|(path, exp)| Some((path, self.matches(exp)?)))
+            .collect()
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", concrete::format(&self.to_exp()).replace(META, "?"))
+    }
+}
+
+/// Rewrites whatever matches `lhs` into `rhs`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rule {
+    lhs: Pattern,
+    rhs: Pattern,
+}
+
+impl Rule {
+    pub fn new(lhs: Pattern, rhs: Pattern) -> Result<Self, PatternError> {
+        let bound = lhs.metavariables();
+        if let Some(meta) = rhs
+            .metavariables()
+            .into_iter()
+            .find(|meta| !bound.contains(meta))
+        {
+            return Err(PatternError::Unbound(meta.to_string()));
+        }
+        Ok(Rule { lhs, rhs })
+    }
+
+    /// Parses `lhs => rhs`, or with `⇒`.
+    pub fn parse(code: &str) -> Result<Self, PatternError> {
+        let (arrow, split) = ["⇒", "=>"]
+            .iter()
+            .find_map(|arrow| Some((arrow, code.find(arrow)?)))
+            .ok_or(PatternError::NoArrow)?;
+        let rhs_start = split + arrow.len();
+        let lhs = Pattern::parse(&code[..split])?;
+        let rhs = Pattern::parse(&code[rhs_start..]).map_err(|error| match error {
+            PatternError::Syntax(SyntaxError { offset }) => PatternError::Syntax(SyntaxError {
+                offset: rhs_start + offset,
+            }),
+            error => error,
+        })?;
+        Self::new(lhs, rhs)
+    }
+
+    pub fn lhs(&self) -> &Pattern {
+        &self.lhs
+    }
+
+    pub fn rhs(&self) -> &Pattern {
+        &self.rhs
+    }
+
+    /// What `exp` rewrites to if it matches.
+    pub fn apply(&self, exp: &Exp) -> Option<Exp> {
+        self.rhs.instantiate(&self.lhs.matches(exp)?)
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ⇒ {}", self.lhs, self.rhs)
+    }
+}
+
+/// Rewrites each subterm after its children, with the first rule which
+/// applies.
+struct BottomUp<'a> {
+    rules: &'a [Rule],
+    changed: bool,
+}
+
+impl ExpFolder for BottomUp<'_> {
+    fn fold_exp(&mut self, exp: Exp, scope: &mut Scope) -> Exp {
+        let exp = walk_fold(self, exp, scope);
+        match self.rules.iter().find_map(|rule| rule.apply(&exp)) {
+            Some(rewritten) => {
+                self.changed = true;
+                rewritten
+            }
+            None => exp,
+        }
+    }
+}
+
+/// One pass over `exp`, rewriting each subterm once after its children, and
+/// whether anything changed.
+pub fn rewrite_bottom_up(exp: &Exp, rules: &[Rule]) -> (Exp, bool) {
+    let mut rewriter = BottomUp {
+        rules,
+        changed: false,
+    };
+    let exp = rewriter.fold(exp.clone());
+    (exp, rewriter.changed)
+}
+
+/// Rewrites `exp` bottom-up until no rule applies anywhere, or gives up
+/// after `pass_limit` passes, since rules need not terminate.
+pub fn rewrite_fixpoint(exp: &Exp, rules: &[Rule], pass_limit: usize) -> Option<Exp> {
+    let mut exp = exp.clone();
+    for _ in 0..pass_limit {
+        let (rewritten, changed) = rewrite_bottom_up(&exp, rules);
+        if !changed {
+            return Some(rewritten);
+        }
+        exp = rewritten;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::syntax::Step::{Argument, LambdaBody};
+
+    fn parse(code: &str) -> Exp {
+        concrete::parse(code).unwrap()
+    }
+
+    #[test]
+    fn test_match() {
+        let pattern = Pattern::parse("case C(?a) of { C(?x) → ?b }").unwrap();
+        assert_eq!(pattern.metavariables(), vec!["a", "x", "b"]);
+        assert_eq!(pattern.to_string(), "case C(?a) of {\n  C(?x) -> ?b\n}");
+        let bindings = pattern
+            .matches(&parse("case C(D()) of { C(y) → f y }"))
+            .unwrap();
+        assert_eq!(bindings["a"], parse("D()"));
+        assert_eq!(bindings["x"], parse("y"));
+        assert_eq!(bindings["b"], parse("f y"));
+        assert_eq!(pattern.matches(&parse("case C(D()) of { E(y) → y }")), None);
+
+        let identity = Pattern::parse("λ?x. ?x").unwrap();
+        assert!(identity.matches(&parse("λy. y")).is_some());
+        assert!(identity.matches(&parse("λy. z")).is_none());
+
+        let found = Pattern::parse("?f Zero()")
+            .unwrap()
+            .find(&parse("λg. C(g Zero(), (λx. x) Zero())"));
+        let paths: Vec<_> = found.iter().map(|(path, _)| path.clone()).collect();
+        assert_eq!(
+            paths,
+            vec![vec![LambdaBody, Argument(0)], vec![LambdaBody, Argument(1)]]
+        );
+
+        assert_eq!(
+            Pattern::parse("C(?a, λ"),
+            Err(PatternError::Syntax(SyntaxError { offset: 6 }))
+        );
+    }
+
+    #[test]
+    fn test_rewrite() {
+        assert_eq!(Rule::parse("?a"), Err(PatternError::NoArrow));
+        assert_eq!(
+            Rule::parse("C(?a) => D(?b)"),
+            Err(PatternError::Unbound("b".to_string()))
+        );
+        let swap = Rule::parse("Pair(?a, ?b) ⇒ Pair(?b, ?a)").unwrap();
+        assert_eq!(swap.to_string(), "Pair(?a, ?b) ⇒ Pair(?b, ?a)");
+        assert_eq!(
+            swap.apply(&parse("Pair(A(), B())")),
+            Some(parse("Pair(B(), A())"))
+        );
+
+        let rules = [
+            Rule::parse("Add(Zero(), ?n) => ?n").unwrap(),
+            Rule::parse("Add(Suc(?m), ?n) => Suc(Add(?m, ?n))").unwrap(),
+        ];
+        let sum = parse("Add(Suc(Suc(Zero())), Add(Suc(Zero()), Zero()))");
+        let (once, changed) = rewrite_bottom_up(&sum, &rules);
+        assert!(changed);
+        assert_eq!(
+            once,
+            parse("Suc(Add(Suc(Zero()), Suc(Add(Zero(), Zero()))))")
+        );
+        assert_eq!(
+            rewrite_fixpoint(&sum, &rules, 10),
+            Some(parse("Suc(Suc(Suc(Zero())))"))
+        );
+        assert_eq!(
+            rewrite_fixpoint(&sum, std::slice::from_ref(&swap), 10),
+            Some(sum)
+        );
+        let forever = Rule::parse("A() => B(A())").unwrap();
+        assert_eq!(rewrite_fixpoint(&parse("A()"), &[forever], 10), None);
+    }
+}
@@ -0,0 +1,533 @@
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::{prelude::wasm_bindgen, JsValue};
+
+use crate::{
+    error::{Error, ErrorKind},
+    lowering::scott::ArityMismatch,
+    syntax::{Branch, Constructor, Exp, Variable},
+};
+
+use super::{instantiate, substitute};
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Status {
+    /// The steps given ran out before the evaluation finished.
+    Running,
+    Done,
+}
+
+/// How much work an evaluation has done.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Statistics {
+    /// Counted as for [`super::eval_with_limit`]: evaluating an expression
+    /// takes at most `n` steps exactly when `eval_with_limit` succeeds with a
+    /// limit of `n`.
+    pub steps: usize,
+    pub beta_reductions: usize,
+    pub branch_matches: usize,
+    pub rec_unfoldings: usize,
+    /// The most subterms waiting for the values of others at once.
+    pub max_depth: usize,
+}
+
+/// What is left to do with the value of the subterm being evaluated.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+enum Frame {
+    /// Apply it to `argument` if it is a λ, else `Apply(f, argument)` is the
+    /// value.
+    Function { f: Box<Exp>, argument: Box<Exp> },
+    /// Substitute it for `x` in `body` as the argument of `λx. body`.
+    Argument { x: Variable, body: Box<Exp> },
+    /// Match it against `branches`, else `Case(e, branches)` is the value.
+    Scrutinee { e: Box<Exp>, branches: Vec<Branch> },
+    /// It is the value of `arguments[evaluated.len()]`.
+    Constructor {
+        constructor: Constructor,
+        arguments: Vec<Exp>,
+        evaluated: Vec<Exp>,
+    },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+enum Control {
+    Eval(Exp),
+    Return(Exp),
+}
+
+/// The branch a value returned to a case expression takes and the arguments
+/// of the value, failing if the branch has another number of parameters.
+fn matching<'a>(
+    value: &'a Exp,
+    branches: &'a [Branch],
+) -> Result<Option<(&'a Branch, &'a [Exp])>, ArityMismatch> {
+    let Exp::Const(constructor, arguments) = value else {
+        return Ok(None);
+    };
+    let Some(branch) = branches
+        .iter()
+        .find(|branch| &branch.constructor == constructor)
+    else {
+        return Ok(None);
+    };
+    if branch.parameters.len() != arguments.len() {
+        return Err(ArityMismatch {
+            constructor: constructor.clone(),
+            expected: branch.parameters.len(),
+            found: arguments.len(),
+        });
+    }
+    Ok(Some((branch, arguments)))
+}
+
+/// Evaluates an expression the way [`super::eval`] does, but a given number
+/// of steps at a time, so that the evaluation can be watched, abandoned, or
+/// saved with [`Evaluator::snapshot`] and continued later.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Evaluator {
+    control: Control,
+    stack: Vec<Frame>,
+    statistics: Statistics,
+}
+
+impl Evaluator {
+    pub fn start(exp: &Exp) -> Self {
+        Evaluator {
+            control: Control::Eval(exp.clone()),
+            stack: vec![],
+            statistics: Statistics::default(),
+        }
+    }
+
+    pub fn steps(&self) -> usize {
+        self.statistics.steps
+    }
+
+    pub fn statistics(&self) -> Statistics {
+        self.statistics
+    }
+
+    /// Continues an evaluation saved by [`Evaluator::snapshot`]. Since a
+    /// snapshot need not come from `snapshot`, one whose frames cannot be
+    /// continued is refused.
+    pub fn restore(snapshot: &str) -> Result<Self, Error> {
+        let evaluator: Self = serde_json::from_str(snapshot)?;
+        evaluator.check()?;
+        Ok(evaluator)
+    }
+
+    /// Checks what [`Evaluator::current_term`] and [`Evaluator::unwind`] take
+    /// for granted of the frames: a constructor frame waits for one of its
+    /// arguments, and a value returned to a case expression has as many
+    /// arguments as the branch it matches has parameters.
+    fn check(&self) -> Result<(), Error> {
+        for frame in &self.stack {
+            if let Frame::Constructor {
+                constructor,
+                arguments,
+                evaluated,
+            } = frame
+            {
+                if evaluated.len() >= arguments.len() {
+                    return Err(Error {
+                        kind: ErrorKind::InvalidArgument,
+                        message: format!(
+                            "{} arguments of constructor {} evaluated, out of {}",
+                            evaluated.len(),
+                            constructor,
+                            arguments.len()
+                        ),
+                        span: None,
+                    });
+                }
+            }
+        }
+        // Later values are matched against branches only after evaluating
+        // further, which fails just as evaluating the program would.
+        let Control::Return(value) = &self.control else {
+            return Ok(());
+        };
+        let mut value = value.clone();
+        for frame in self.stack.iter().rev() {
+            match frame {
+                Frame::Constructor {
+                    constructor,
+                    arguments,
+                    evaluated,
+                } if evaluated.len() + 1 == arguments.len() => {
+                    let mut arguments = evaluated.clone();
+                    arguments.push(value);
+                    value = Exp::Const(constructor.clone(), arguments);
+                }
+                Frame::Scrutinee { branches, .. } => {
+                    matching(&value, branches)?;
+                    break;
+                }
+                _ => break,
+            }
+        }
+        Ok(())
+    }
+
+    /// The value, once the evaluation is done.
+    pub fn result(&self) -> Option<&Exp> {
+        match &self.control {
+            Control::Return(value) if self.stack.is_empty() => Some(value),
+            _ => None,
+        }
+    }
+
+    /// The expression with the subterm being evaluated replaced by what it has
+    /// been reduced to so far.
+    pub fn current_term(&self) -> Exp {
+        let (Control::Eval(exp) | Control::Return(exp)) = &self.control;
+        self.stack
+            .iter()
+            .rev()
+            .fold(exp.clone(), |term, frame| match frame {
+                Frame::Function { argument, .. } => Exp::Apply(Box::new(term), argument.clone()),
+                Frame::Argument { x, body } => Exp::Apply(
+                    Box::new(Exp::Lambda(x.clone(), body.clone())),
+                    Box::new(term),
+                ),
+                Frame::Scrutinee { branches, .. } => Exp::Case(Box::new(term), branches.clone()),
+                Frame::Constructor {
+                    constructor,
+                    arguments,
+                    evaluated,
+                } => {
+                    let mut arguments_now = evaluated.clone();
+                    arguments_now.push(term);
+                    arguments_now.extend_from_slice(&arguments[evaluated.len() + 1..]);
+                    Exp::Const(constructor.clone(), arguments_now)
+                }
+            })
+    }
+
+    /// Takes up to `steps` more steps. Panics as [`super::eval`] does when a
+    /// value is matched against a branch with another number of parameters.
+    pub fn run(&mut self, steps: usize) -> Status {
+        self.try_run(steps)
+            .unwrap_or_else(|mismatch| panic!("Number of parameters does not match: {}", mismatch))
+    }
+
+    /// Like [`Evaluator::run`], but fails instead of panicking, leaving the
+    /// evaluation where the branch was to be taken.
+    pub fn try_run(&mut self, steps: usize) -> Result<Status, ArityMismatch> {
+        for _ in 0..steps {
+            self.unwind()?;
+            match std::mem::replace(&mut self.control, Control::Eval(Exp::Var(String::new()))) {
+                Control::Eval(exp) => {
+                    self.statistics.steps += 1;
+                    self.control = self.eval(exp);
+                    self.statistics.max_depth = self.statistics.max_depth.max(self.stack.len());
+                }
+                done @ Control::Return(_) => {
+                    self.control = done;
+                    break;
+                }
+            }
+        }
+        // Returning a value takes no steps, so the evaluation may be done
+        // without another one.
+        self.unwind()?;
+        Ok(self.status())
+    }
+
+    pub fn status(&self) -> Status {
+        if self.result().is_some() {
+            Status::Done
+        } else {
+            Status::Running
+        }
+    }
+
+    /// One step: starts evaluating `exp`.
+    fn eval(&mut self, exp: Exp) -> Control {
+        match exp {
+            Exp::Var(_) | Exp::Lambda(..) => Control::Return(exp),
+            Exp::Apply(f, argument) => {
+                self.stack.push(Frame::Function {
+                    f: f.clone(),
+                    argument,
+                });
+                Control::Eval(*f)
+            }
+            Exp::Case(e, branches) => {
+                self.stack.push(Frame::Scrutinee {
+                    e: e.clone(),
+                    branches,
+                });
+                Control::Eval(*e)
+            }
+            Exp::Const(constructor, arguments) => match arguments.first().cloned() {
+                Some(first) => {
+                    self.stack.push(Frame::Constructor {
+                        constructor,
+                        arguments,
+                        evaluated: vec![],
+                    });
+                    Control::Eval(first)
+                }
+                None => Control::Return(Exp::Const(constructor, arguments)),
+            },
+            Exp::Rec(x, body) => {
+                self.statistics.rec_unfoldings += 1;
+                let unfolded = Exp::Rec(x.clone(), body.clone());
+                Control::Eval(substitute(&body, &x, &unfolded))
+            }
+        }
+    }
+
+    /// Passes values to the frames waiting for them until a subterm is to be
+    /// evaluated or the evaluation is done.
+    fn unwind(&mut self) -> Result<(), ArityMismatch> {
+        while let Control::Return(value) = &self.control {
+            let frame = match self.stack.pop() {
+                Some(frame) => frame,
+                None => return Ok(()),
+            };
+            let value = value.clone();
+            self.control = match frame {
+                Frame::Function { f, argument } => match value {
+                    Exp::Lambda(x, body) => {
+                        self.stack.push(Frame::Argument { x, body });
+                        Control::Eval(*argument)
+                    }
+                    _ => Control::Return(Exp::Apply(f, argument)),
+                },
+                Frame::Argument { x, body } => {
+                    self.statistics.beta_reductions += 1;
+                    Control::Eval(substitute(&body, &x, &value))
+                }
+                Frame::Scrutinee { e, branches } => {
+                    let branch = match matching(&value, &branches) {
+                        Ok(branch) => branch,
+                        Err(mismatch) => {
+                            self.stack.push(Frame::Scrutinee { e, branches });
+                            return Err(mismatch);
+                        }
+                    };
+                    match branch {
+                        Some((branch, arguments)) => {
+                            self.statistics.branch_matches += 1;
+                            Control::Eval(instantiate(arguments, branch))
+                        }
+                        None => Control::Return(Exp::Case(e, branches)),
+                    }
+                }
+                Frame::Constructor {
+                    constructor,
+                    arguments,
+                    mut evaluated,
+                } => {
+                    evaluated.push(value);
+                    match arguments.get(evaluated.len()).cloned() {
+                        Some(next) => {
+                            self.stack.push(Frame::Constructor {
+                                constructor,
+                                arguments,
+                                evaluated,
+                            });
+                            Control::Eval(next)
+                        }
+                        None => Control::Return(Exp::Const(constructor, evaluated)),
+                    }
+                }
+            };
+        }
+        Ok(())
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Evaluator {
+    /// The state of the evaluation as JSON, to be continued with
+    /// [`Evaluator::restore`].
+    pub fn snapshot(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.result().is_some()
+    }
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+impl Evaluator {
+    /// @throws {ChiError}
+    #[wasm_bindgen(constructor)]
+    pub fn new(exp: JsValue) -> Result<Evaluator, Error> {
+        let exp: Exp = serde_wasm_bindgen::from_value(exp)?;
+        Ok(Self::start(&exp))
+    }
+
+    /// @throws {ChiError}
+    #[wasm_bindgen(js_name = restore)]
+    pub fn restore_js(snapshot: &str) -> Result<Evaluator, Error> {
+        Self::restore(snapshot)
+    }
+
+    /// Takes up to `steps` more steps, failing where a value is matched
+    /// against a branch with another number of parameters.
+    ///
+    /// @throws {ChiError}
+    #[wasm_bindgen(js_name = run)]
+    pub fn run_js(&mut self, steps: usize) -> Result<Status, Error> {
+        Ok(self.try_run(steps)?)
+    }
+
+    #[wasm_bindgen(js_name = current_term)]
+    pub fn current_term_js(&self) -> Result<JsValue, Error> {
+        Ok(serde_wasm_bindgen::to_value(&self.current_term())?)
+    }
+
+    /// The value, or `undefined` while the evaluation is running.
+    ///
+    /// @throws {ChiError}
+    #[wasm_bindgen(js_name = result)]
+    pub fn result_js(&self) -> Result<JsValue, Error> {
+        Ok(serde_wasm_bindgen::to_value(&self.result())?)
+    }
+
+    #[wasm_bindgen(getter, js_name = steps)]
+    pub fn steps_js(&self) -> usize {
+        self.steps()
+    }
+
+    /// @throws {ChiError}
+    #[wasm_bindgen(js_name = statistics)]
+    pub fn statistics_js(&self) -> Result<JsValue, Error> {
+        Ok(serde_wasm_bindgen::to_value(&self.statistics)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        semantic::{eval, eval_with_limit},
+        syntax::concrete,
+    };
+
+    #[test]
+    fn test_evaluator() {
+        let code = r#"(rec add = λm. λn. case n of {
+            Zero() → m;
+            Suc(n) → Suc(add m n)
+        }) Suc(Zero()) Suc(Suc(Zero()))"#;
+        let exp = concrete::parse(code).unwrap();
+        let mut evaluator = Evaluator::start(&exp);
+        let mut terms = vec![];
+        while evaluator.run(5) == Status::Running {
+            let term = evaluator.current_term();
+            assert_eq!(eval(&term), eval(&exp));
+            terms.push(term);
+        }
+        assert!(terms.len() > 1);
+        assert_eq!(evaluator.result(), Some(&eval(&exp)));
+        assert_eq!(evaluator.current_term(), eval(&exp));
+
+        let steps = evaluator.steps();
+        assert!(eval_with_limit(&exp, steps).is_some());
+        assert_eq!(eval_with_limit(&exp, steps - 1), None);
+
+        let exp = concrete::parse("case C(λx.x) of { C(f) → f (λy.y) D() }").unwrap();
+        let mut evaluator = Evaluator::start(&exp);
+        assert_eq!(evaluator.run(usize::MAX), Status::Done);
+        assert_eq!(evaluator.result(), Some(&eval(&exp)));
+
+        let mut evaluator = Evaluator::start(&concrete::parse("rec x = x").unwrap());
+        assert_eq!(evaluator.run(1000), Status::Running);
+        assert_eq!(evaluator.steps(), 1000);
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let code = r#"(rec add = λm. λn. case n of {
+            Zero() → m;
+            Suc(n) → Suc(add m n)
+        }) Suc(Suc(Zero())) Suc(Suc(Zero()))"#;
+        let exp = concrete::parse(code).unwrap();
+        let mut uninterrupted = Evaluator::start(&exp);
+        uninterrupted.run(usize::MAX);
+
+        let mut evaluator = Evaluator::start(&exp);
+        while evaluator.run(7) == Status::Running {
+            evaluator = Evaluator::restore(&evaluator.snapshot()).unwrap();
+        }
+        assert_eq!(evaluator.result(), Some(&eval(&exp)));
+        assert_eq!(evaluator.statistics(), uninterrupted.statistics());
+        assert_eq!(
+            evaluator.statistics(),
+            Statistics {
+                steps: evaluator.steps(),
+                beta_reductions: 6,
+                branch_matches: 3,
+                rec_unfoldings: 3,
+                max_depth: evaluator.statistics().max_depth,
+            }
+        );
+        assert!(Evaluator::restore("{}").is_err());
+
+        // A constructor used with two arities evaluates, so it has to
+        // restore.
+        let exp = concrete::parse("(λx. case C(x) of { C(y) → C(y, y) }) D()").unwrap();
+        let mut evaluator = Evaluator::start(&exp);
+        while evaluator.run(1) == Status::Running {
+            evaluator = Evaluator::restore(&evaluator.snapshot()).unwrap();
+        }
+        assert_eq!(evaluator.result(), Some(&eval(&exp)));
+    }
+
+    #[test]
+    fn test_try_run() {
+        let exp = concrete::parse("case C(D()) of { C() → E() }").unwrap();
+        let mut evaluator = Evaluator::start(&exp);
+        let mismatch = evaluator.try_run(usize::MAX).unwrap_err();
+        assert_eq!(
+            (
+                mismatch.constructor.as_str(),
+                mismatch.expected,
+                mismatch.found
+            ),
+            ("C", 0, 1)
+        );
+        assert_eq!(
+            evaluator.current_term(),
+            concrete::parse("case C(D()) of { C() → E() }").unwrap()
+        );
+        assert_eq!(evaluator.try_run(1), Err(mismatch));
+
+        // The constructor only matters where a branch is taken.
+        let exp = concrete::parse("case C() of { C() → C(D()) }").unwrap();
+        let mut evaluator = Evaluator::start(&exp);
+        assert_eq!(evaluator.try_run(usize::MAX), Ok(Status::Done));
+        assert_eq!(evaluator.result(), Some(&eval(&exp)));
+    }
+
+    #[test]
+    fn test_restore_edited_snapshot() {
+        let exp = concrete::parse("case C(D()) of { C(x) → x }").unwrap();
+        let mut evaluator = Evaluator::start(&exp);
+        evaluator.run(2);
+        let snapshot = evaluator.snapshot();
+        assert!(Evaluator::restore(&snapshot).is_ok());
+
+        // A value returned to a branch of another arity would reach the panic
+        // in `unwind`.
+        let returned = snapshot.replace(r#""control":{"Eval""#, r#""control":{"Return""#);
+        assert!(Evaluator::restore(&returned).is_ok());
+        let edited = returned.replace(r#""parameters":["x"]"#, r#""parameters":[]"#);
+        let error = Evaluator::restore(&edited).unwrap_err();
+        assert_eq!(error.kind, ErrorKind::ArityMismatch);
+
+        // Neither may a constructor frame with every argument evaluated.
+        let edited = snapshot.replace(r#""evaluated":[]"#, r#""evaluated":[{"Const":["D",[]]}]"#);
+        let error = Evaluator::restore(&edited).unwrap_err();
+        assert_eq!(error.kind, ErrorKind::InvalidArgument);
+    }
+}
@@ -1,30 +1,63 @@
 use crate::syntax::{Branch, Exp};
 
+mod evaluator;
+mod reduction;
+mod rename;
+mod scope;
+mod simplify;
 mod substitute;
+mod termination;
+pub use evaluator::{Evaluator, Statistics, Status};
+pub use reduction::{contract, redex_kind, redexes, RedexKind};
+pub use rename::{rename_binder, RenameError};
+pub use scope::{resolve, Binder, BinderId, BinderPath, ScopeInfo};
+pub use simplify::{simplify, simplify_with_limit, DEFAULT_REDUCTION_LIMIT};
 pub use substitute::substitute;
+pub use termination::{check_termination, Reason, RecReport, Verdict};
 
-fn eval_branch(arguments: &[Exp], branch: &Branch) -> Exp {
+/// Bookkeeping of an evaluation: the steps left and who to tell about each
+/// reduction.
+struct Evaluation<'a> {
+    fuel: usize,
+    observe: &'a mut dyn FnMut(&Exp),
+}
+
+impl Evaluation<'_> {
+    /// Evaluates `exp`, the result of a reduction.
+    fn reduced(&mut self, exp: &Exp) -> Option<Exp> {
+        (self.observe)(exp);
+        eval_with(exp, self)
+    }
+}
+
+/// The expression of `branch` with the parameters replaced by `arguments`.
+fn instantiate(arguments: &[Exp], branch: &Branch) -> Exp {
     let bindings = Iterator::zip(branch.parameters.iter(), arguments.iter());
     let mut result = *(branch.expression.clone());
     for (var, exp) in bindings.rev() {
         result = substitute(&result, var, exp);
     }
-    eval(&result)
+    result
 }
 
-pub fn eval(exp: &Exp) -> Exp {
-    match exp {
+fn eval_branch(arguments: &[Exp], branch: &Branch, evaluation: &mut Evaluation) -> Option<Exp> {
+    evaluation.reduced(&instantiate(arguments, branch))
+}
+
+fn eval_with(exp: &Exp, evaluation: &mut Evaluation) -> Option<Exp> {
+    evaluation.fuel = evaluation.fuel.checked_sub(1)?;
+    Some(match exp {
         Exp::Var(x) => Exp::Var(x.clone()),
         Exp::Apply(f, param) => {
-            if let Exp::Lambda(x, exp) = eval(f) {
-                let param = eval(param);
-                eval(&substitute(&exp, &x, &param))
+            if let Exp::Lambda(x, exp) = eval_with(f, evaluation)? {
+                let param = eval_with(param, evaluation)?;
+                evaluation.reduced(&substitute(&exp, &x, &param))?
             } else {
                 Exp::Apply(f.clone(), param.clone())
             }
         }
         Exp::Case(e, branches) => {
-            let exp = eval(e);
+            let exp = eval_with(e, evaluation)?;
             if let Exp::Const(constructor, exps) = exp {
                 if let Some(branch) = branches
                     .iter()
@@ -33,22 +66,50 @@ pub fn eval(exp: &Exp) -> Exp {
                     if branch.parameters.len() != exps.len() {
                         panic!("Number of parameters does not match");
                     }
-                    return eval_branch(&exps, branch);
+                    return eval_branch(&exps, branch, evaluation);
                 }
             }
             Exp::Case(e.clone(), branches.clone())
         }
         Exp::Lambda(x, exp) => Exp::Lambda(x.clone(), exp.clone()),
-        Exp::Const(constructor, exps) => {
-            Exp::Const(constructor.clone(), exps.iter().map(eval).collect())
+        Exp::Const(constructor, exps) => Exp::Const(
+            constructor.clone(),
+            exps.iter()
+                .map(|exp| eval_with(exp, evaluation))
+                .collect::<Option<_>>()?,
+        ),
+        Exp::Rec(x, exp) => {
+            evaluation.reduced(&substitute(exp, x, &Exp::Rec(x.clone(), exp.clone())))?
         }
-        Exp::Rec(x, exp) => eval(&substitute(exp, x, &Exp::Rec(x.clone(), exp.clone()))),
-    }
+    })
+}
+
+pub fn eval(exp: &Exp) -> Exp {
+    eval_with_limit(exp, usize::MAX).unwrap()
+}
+
+/// Like [`eval`], but gives up after `step_limit` steps, each step being the
+/// evaluation of one subterm.
+pub fn eval_with_limit(exp: &Exp, step_limit: usize) -> Option<Exp> {
+    eval_traced(exp, step_limit, |_| {})
+}
+
+/// Like [`eval_with_limit`], calling `observe` with the result of every β
+/// reduction, matched branch and unfolding of `rec` before evaluating it.
+pub fn eval_traced(exp: &Exp, step_limit: usize, mut observe: impl FnMut(&Exp)) -> Option<Exp> {
+    let mut evaluation = Evaluation {
+        fuel: step_limit,
+        observe: &mut observe,
+    };
+    eval_with(exp, &mut evaluation)
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{semantic::eval, syntax::concrete};
+    use crate::{
+        semantic::{eval, eval_traced, eval_with_limit},
+        syntax::concrete,
+    };
 
     #[test]
     fn test_eval() {
@@ -88,6 +149,24 @@ mod tests {
         assert_eq!(concrete::format(&result), "λx.x");
     }
 
+    #[test]
+    fn test_eval_with_limit() {
+        let term = concrete::parse("(λx.x) Zero()").unwrap();
+        assert_eq!(eval_with_limit(&term, 2), None);
+        assert_eq!(eval_with_limit(&term, 10), Some(eval(&term)));
+        let term = concrete::parse("rec x = x").unwrap();
+        assert_eq!(eval_with_limit(&term, 1000), None);
+    }
+
+    #[test]
+    fn test_eval_traced() {
+        let term = concrete::parse("case (λx.x) C(D()) of { C(y) → y }").unwrap();
+        let mut steps = vec![];
+        let result = eval_traced(&term, 100, |exp| steps.push(concrete::format(exp)));
+        assert_eq!(result, Some(eval(&term)));
+        assert_eq!(steps, vec!["C(D())", "D()"]);
+    }
+
     #[test]
     #[should_panic]
     fn test_eval_too_much_arg() {
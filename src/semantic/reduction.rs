@@ -0,0 +1,118 @@
+use serde::{Deserialize, Serialize};
+
+use crate::syntax::{Exp, Path, Step};
+
+use super::{instantiate, substitute};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RedexKind {
+    /// `(λx. e) e'`
+    Beta,
+    /// `case C(e₁, …, eₙ) of { … C(x₁, …, xₙ) → e; … }`
+    Case,
+    /// `rec x = e`
+    Rec,
+}
+
+/// What kind of redex `exp` is, if it is one. A `case` on a constructor
+/// whose branch has another number of parameters is not.
+pub fn redex_kind(exp: &Exp) -> Option<RedexKind> {
+    match exp {
+        Exp::Apply(f, _) if matches!(**f, Exp::Lambda(..)) => Some(RedexKind::Beta),
+        Exp::Case(e, branches) => match &**e {
+            Exp::Const(constructor, arguments) => branches
+                .iter()
+                .find(|branch| &branch.constructor == constructor)
+                .filter(|branch| branch.parameters.len() == arguments.len())
+                .map(|_| RedexKind::Case),
+            _ => None,
+        },
+        Exp::Rec(..) => Some(RedexKind::Rec),
+        _ => None,
+    }
+}
+
+/// Every redex in `exp`, outermost first, wherever it is: also under λ, in
+/// arguments not yet evaluated and in branches not taken.
+pub fn redexes(exp: &Exp) -> Vec<(Path, RedexKind)> {
+    exp.subterms()
+        .into_iter()
+        .filter_map(|(path, exp)| Some((path, redex_kind(exp)?)))
+        .collect()
+}
+
+/// `exp` with the redex at `path` contracted, or `None` if there is no redex
+/// there. As in evaluation the substitutions do not rename binders, so
+/// contracting under a binder can capture variables of open terms.
+pub fn contract(exp: &Exp, path: &[Step]) -> Option<Exp> {
+    let reduct = match exp.get(path)? {
+        Exp::Apply(f, x) => match &**f {
+            Exp::Lambda(y, body) => substitute(body, y, x),
+            _ => return None,
+        },
+        Exp::Case(e, branches) => match &**e {
+            Exp::Const(constructor, arguments) => {
+                let branch = branches
+                    .iter()
+                    .find(|branch| &branch.constructor == constructor)
+                    .filter(|branch| branch.parameters.len() == arguments.len())?;
+                instantiate(arguments, branch)
+            }
+            _ => return None,
+        },
+        Exp::Rec(x, body) => substitute(body, x, &Exp::Rec(x.clone(), body.clone())),
+        _ => return None,
+    };
+    let mut exp = exp.clone();
+    exp.replace(path, reduct).ok()?;
+    Some(exp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        semantic::eval,
+        syntax::{concrete, Step::*},
+    };
+
+    #[test]
+    fn test_redexes() {
+        let exp =
+            concrete::parse("λf. case C(D()) of { C(x) → (λy. y) x; D() → rec z = z; C() → E() }")
+                .unwrap();
+        assert_eq!(
+            redexes(&exp),
+            vec![
+                (vec![LambdaBody], RedexKind::Case),
+                (vec![LambdaBody, Branch(0), BranchBody], RedexKind::Beta),
+                (vec![LambdaBody, Branch(1), BranchBody], RedexKind::Rec),
+            ]
+        );
+        let exp = concrete::parse("case C(D()) of { C() → E(); C(x) → x }").unwrap();
+        assert_eq!(redexes(&exp), vec![]);
+
+        let exp = concrete::parse("(λx. C(x, x)) ((λy. y) D())").unwrap();
+        let inner = contract(&exp, &[ApplyArg]).unwrap();
+        assert_eq!(inner, concrete::parse("(λx. C(x, x)) D()").unwrap());
+        let outer = contract(&exp, &[]).unwrap();
+        assert_eq!(
+            outer,
+            concrete::parse("C((λy. y) D(), (λy. y) D())").unwrap()
+        );
+        assert_eq!(contract(&exp, &[ApplyFun]), None);
+        assert_eq!(contract(&exp, &[Argument(0)]), None);
+
+        // Always contracting the outermost redex reaches the value eval
+        // computes.
+        let mut exp = concrete::parse(
+            "(rec add = λm. λn. case n of { Zero() → m; Suc(n) → Suc(add m n) }) Suc(Zero()) Suc(Zero())",
+        )
+        .unwrap();
+        let value = eval(&exp);
+        while let Some((path, _)) = redexes(&exp).first() {
+            exp = contract(&exp, path).unwrap();
+        }
+        assert_eq!(exp, value);
+    }
+}
@@ -0,0 +1,251 @@
+use std::{collections::HashSet, fmt};
+
+use serde::{Deserialize, Serialize};
+
+use crate::syntax::{is_variable_name, Branch, Exp, Path, Step, Variable};
+
+use super::scope::{resolve, BinderPath};
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RenameError {
+    /// Nothing is bound at the given path.
+    NoBinder(BinderPath),
+    InvalidName(Variable),
+    /// An occurrence of the renamed binder would be bound by the binder at
+    /// this path instead.
+    Captured {
+        occurrence: Path,
+        by: BinderPath,
+    },
+    /// The occurrence at this path, free or bound elsewhere, would be bound by
+    /// the renamed binder.
+    Captures {
+        occurrence: Path,
+    },
+}
+
+impl fmt::Display for RenameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RenameError::NoBinder(_) => write!(f, "there is no binder to rename"),
+            RenameError::InvalidName(name) => write!(f, "`{}` is not a variable name", name),
+            RenameError::Captured { .. } => write!(
+                f,
+                "an occurrence of the variable would be captured by an inner binder"
+            ),
+            RenameError::Captures { .. } => write!(
+                f,
+                "another variable of that name would be captured by the binder"
+            ),
+        }
+    }
+}
+
+struct Renaming<'a> {
+    binder: &'a BinderPath,
+    occurrences: HashSet<&'a Path>,
+    new_name: &'a str,
+    path: Path,
+}
+
+impl Renaming<'_> {
+    fn child(&mut self, step: Step, exp: &Exp) -> Box<Exp> {
+        self.path.push(step);
+        let result = self.rename(exp);
+        self.path.pop();
+        Box::new(result)
+    }
+
+    fn name(&self, name: &Variable, parameter: Option<usize>) -> Variable {
+        if self.binder.path == self.path && self.binder.parameter == parameter {
+            self.new_name.to_string()
+        } else {
+            name.clone()
+        }
+    }
+
+    fn rename(&mut self, exp: &Exp) -> Exp {
+        match exp {
+            Exp::Var(_) if self.occurrences.contains(&self.path) => {
+                Exp::Var(self.new_name.to_string())
+            }
+            Exp::Var(x) => Exp::Var(x.clone()),
+            Exp::Apply(f, x) => {
+                Exp::Apply(self.child(Step::ApplyFun, f), self.child(Step::ApplyArg, x))
+            }
+            Exp::Lambda(x, body) => {
+                Exp::Lambda(self.name(x, None), self.child(Step::LambdaBody, body))
+            }
+            Exp::Rec(x, body) => Exp::Rec(self.name(x, None), self.child(Step::RecBody, body)),
+            Exp::Case(e, branches) => Exp::Case(
+                self.child(Step::Scrutinee, e),
+                branches
+                    .iter()
+                    .enumerate()
+                    .map(|(i, branch)| {
+                        self.path.push(Step::Branch(i));
+                        let branch = Branch {
+                            constructor: branch.constructor.clone(),
+                            parameters: branch
+                                .parameters
+                                .iter()
+                                .enumerate()
+                                .map(|(j, parameter)| self.name(parameter, Some(j)))
+                                .collect(),
+                            expression: self.child(Step::BranchBody, &branch.expression),
+                        };
+                        self.path.pop();
+                        branch
+                    })
+                    .collect(),
+            ),
+            Exp::Const(constructor, arguments) => Exp::Const(
+                constructor.clone(),
+                arguments
+                    .iter()
+                    .enumerate()
+                    .map(|(i, argument)| *self.child(Step::Argument(i), argument))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// Renames the variable bound at `binder` and exactly the occurrences it
+/// binds, refusing if that would change what any occurrence refers to.
+pub fn rename_binder(exp: &Exp, binder: &BinderPath, new_name: &str) -> Result<Exp, RenameError> {
+    if !is_variable_name(new_name) {
+        return Err(RenameError::InvalidName(new_name.to_string()));
+    }
+    let before = resolve(exp);
+    let id = before
+        .find_binder(binder)
+        .ok_or_else(|| RenameError::NoBinder(binder.clone()))?;
+    let result = Renaming {
+        binder,
+        occurrences: before.occurrences(id).collect(),
+        new_name,
+        path: vec![],
+    }
+    .rename(exp);
+
+    // Binders and occurrences keep their paths, so the scoping is unchanged
+    // exactly when every occurrence resolves to the binder it did before.
+    let after = resolve(&result);
+    for (occurrence, old) in &before.bound {
+        let new = after.binder_of(occurrence);
+        if *old == id && new != Some(id) {
+            return Err(RenameError::Captured {
+                occurrence: occurrence.clone(),
+                by: after.binders[new.unwrap()].at.clone(),
+            });
+        }
+        if *old != id && new != Some(*old) {
+            return Err(RenameError::Captures {
+                occurrence: occurrence.clone(),
+            });
+        }
+    }
+    if let Some((occurrence, _)) = before
+        .free
+        .iter()
+        .find(|(occurrence, _)| after.binder_of(occurrence).is_some())
+    {
+        return Err(RenameError::Captures {
+            occurrence: occurrence.clone(),
+        });
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::syntax::{concrete, Step::*};
+
+    fn rename(
+        code: &str,
+        path: Path,
+        parameter: Option<usize>,
+        to: &str,
+    ) -> Result<String, RenameError> {
+        let exp = concrete::parse(code).unwrap();
+        rename_binder(&exp, &BinderPath { path, parameter }, to).map(|exp| concrete::format(&exp))
+    }
+
+    const CODE: &str = "λx. case x of { C(x, y) → x y; D() → x }";
+
+    #[test]
+    fn test_rename_lambda() {
+        assert_eq!(
+            rename(CODE, vec![], None, "z"),
+            Ok("λz.case z of {\n  C(x, y) -> x y;\n  D() -> z\n}".to_string())
+        );
+    }
+
+    #[test]
+    fn test_rename_rec() {
+        assert_eq!(
+            rename("rec f = λx. f x", vec![], None, "g"),
+            Ok("rec g = λx.(g x)".to_string())
+        );
+        assert!(matches!(
+            rename("rec f = λx. f x", vec![], None, "x"),
+            Err(RenameError::Captured { .. })
+        ));
+    }
+
+    #[test]
+    fn test_rename_parameter() {
+        assert_eq!(
+            rename(CODE, vec![LambdaBody, Branch(0)], Some(0), "a"),
+            Ok("λx.case x of {\n  C(a, y) -> a y;\n  D() -> x\n}".to_string())
+        );
+    }
+
+    #[test]
+    fn test_rename_captured() {
+        assert_eq!(
+            rename("λx. case x of { C(y) → x }", vec![], None, "y"),
+            Err(RenameError::Captured {
+                occurrence: vec![LambdaBody, Branch(0), BranchBody],
+                by: BinderPath {
+                    path: vec![LambdaBody, Branch(0)],
+                    parameter: Some(0)
+                }
+            })
+        );
+        assert!(matches!(
+            rename(CODE, vec![LambdaBody, Branch(0)], Some(0), "y"),
+            Err(RenameError::Captured { .. })
+        ));
+    }
+
+    #[test]
+    fn test_rename_captures() {
+        assert_eq!(
+            rename("λx. λy. x z", vec![LambdaBody], None, "z"),
+            Err(RenameError::Captures {
+                occurrence: vec![LambdaBody, LambdaBody, ApplyArg]
+            })
+        );
+        assert_eq!(
+            rename("λx. λy. x", vec![LambdaBody], None, "x"),
+            Err(RenameError::Captures {
+                occurrence: vec![LambdaBody, LambdaBody]
+            })
+        );
+    }
+
+    #[test]
+    fn test_rename_invalid() {
+        assert!(matches!(
+            rename("λx. x", vec![], None, "rec"),
+            Err(RenameError::InvalidName(_))
+        ));
+        assert!(matches!(
+            rename("λx. x", vec![LambdaBody], None, "y"),
+            Err(RenameError::NoBinder(_))
+        ));
+    }
+}
@@ -0,0 +1,213 @@
+use serde::{Deserialize, Serialize};
+
+use crate::syntax::{Exp, Path, Step, Variable};
+
+/// An index into [`ScopeInfo::binders`].
+pub type BinderId = usize;
+
+/// Where a variable is bound: the λ or `rec` at `path`, or a parameter of the
+/// branch at `path`, which ends with [`Step::Branch`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct BinderPath {
+    pub path: Path,
+    /// The position among the parameters, for a branch.
+    pub parameter: Option<usize>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Binder {
+    pub name: Variable,
+    pub at: BinderPath,
+}
+
+/// Which binder every variable occurrence of an expression refers to.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScopeInfo {
+    /// Every binder, in the order they appear.
+    pub binders: Vec<Binder>,
+    /// The bound occurrences, in the order they appear.
+    pub bound: Vec<(Path, BinderId)>,
+    /// The free occurrences, in the order they appear.
+    pub free: Vec<(Path, Variable)>,
+}
+
+impl ScopeInfo {
+    /// The binder of the occurrence at `path`.
+    pub fn binder_of(&self, path: &[Step]) -> Option<BinderId> {
+        self.bound
+            .iter()
+            .find(|(occurrence, _)| occurrence == path)
+            .map(|(_, binder)| *binder)
+    }
+
+    pub fn find_binder(&self, at: &BinderPath) -> Option<BinderId> {
+        self.binders.iter().position(|binder| binder.at == *at)
+    }
+
+    /// The occurrences bound by `binder`.
+    pub fn occurrences(&self, binder: BinderId) -> impl Iterator<Item = &Path> {
+        self.bound
+            .iter()
+            .filter(move |(_, id)| *id == binder)
+            .map(|(path, _)| path)
+    }
+}
+
+struct Resolver {
+    info: ScopeInfo,
+    path: Path,
+    /// The binders in scope, innermost last.
+    scope: Vec<(Variable, BinderId)>,
+}
+
+impl Resolver {
+    fn bind(&mut self, name: &Variable, parameter: Option<usize>) {
+        let id = self.info.binders.len();
+        self.info.binders.push(Binder {
+            name: name.clone(),
+            at: BinderPath {
+                path: self.path.clone(),
+                parameter,
+            },
+        });
+        self.scope.push((name.clone(), id));
+    }
+
+    fn child(&mut self, step: Step, exp: &Exp) {
+        self.path.push(step);
+        self.resolve(exp);
+        self.path.pop();
+    }
+
+    fn resolve(&mut self, exp: &Exp) {
+        match exp {
+            Exp::Var(x) => match self.scope.iter().rev().find(|(name, _)| name == x) {
+                Some((_, id)) => self.info.bound.push((self.path.clone(), *id)),
+                None => self.info.free.push((self.path.clone(), x.clone())),
+            },
+            Exp::Apply(f, x) => {
+                self.child(Step::ApplyFun, f);
+                self.child(Step::ApplyArg, x);
+            }
+            Exp::Lambda(x, body) => {
+                self.bind(x, None);
+                self.child(Step::LambdaBody, body);
+                self.scope.pop();
+            }
+            Exp::Rec(x, body) => {
+                self.bind(x, None);
+                self.child(Step::RecBody, body);
+                self.scope.pop();
+            }
+            Exp::Case(e, branches) => {
+                self.child(Step::Scrutinee, e);
+                for (i, branch) in branches.iter().enumerate() {
+                    self.path.push(Step::Branch(i));
+                    for (j, parameter) in branch.parameters.iter().enumerate() {
+                        self.bind(parameter, Some(j));
+                    }
+                    self.child(Step::BranchBody, &branch.expression);
+                    let depth = self.scope.len() - branch.parameters.len();
+                    self.scope.truncate(depth);
+                    self.path.pop();
+                }
+            }
+            Exp::Const(_, arguments) => {
+                for (i, argument) in arguments.iter().enumerate() {
+                    self.child(Step::Argument(i), argument);
+                }
+            }
+        }
+    }
+}
+
+/// Resolves every variable occurrence of `exp` to its binder. A `rec` binds
+/// its variable in its own body, and a later parameter of a branch shadows an
+/// earlier one of the same name, as in evaluation.
+pub fn resolve(exp: &Exp) -> ScopeInfo {
+    let mut resolver = Resolver {
+        info: ScopeInfo::default(),
+        path: vec![],
+        scope: vec![],
+    };
+    resolver.resolve(exp);
+    resolver.info
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::syntax::{concrete, Step::*};
+
+    fn resolved(code: &str) -> ScopeInfo {
+        resolve(&concrete::parse(code).unwrap())
+    }
+
+    #[test]
+    fn test_binders() {
+        let info = resolved("rec f = λx. case x of { C(x, y) → f x y; D(x, x) → x }");
+        let names: Vec<_> = info.binders.iter().map(|b| b.name.as_str()).collect();
+        assert_eq!(names, vec!["f", "x", "x", "y", "x", "x"]);
+        assert_eq!(
+            info.binders[1].at,
+            BinderPath {
+                path: vec![RecBody],
+                parameter: None
+            }
+        );
+        assert_eq!(
+            info.binders[5].at,
+            BinderPath {
+                path: vec![RecBody, LambdaBody, Branch(1)],
+                parameter: Some(1)
+            }
+        );
+        assert_eq!(info.find_binder(&info.binders[3].at), Some(3));
+    }
+
+    #[test]
+    fn test_rec_binds_in_body() {
+        let info = resolved("rec f = λx. f x");
+        assert_eq!(info.binder_of(&[RecBody, LambdaBody, ApplyFun]), Some(0));
+        assert_eq!(info.binder_of(&[RecBody, LambdaBody, ApplyArg]), Some(1));
+        assert_eq!(info.occurrences(0).count(), 1);
+    }
+
+    #[test]
+    fn test_branch_parameters() {
+        let info = resolved("λx. case x of { C(x, y) → x y; D(x, x) → x; E() → x }");
+        // The parameters shadow the λ, and a later parameter an earlier one.
+        let body = |i: usize| vec![LambdaBody, Branch(i), BranchBody];
+        assert_eq!(info.binder_of(&[body(0), vec![ApplyFun]].concat()), Some(1));
+        assert_eq!(info.binder_of(&[body(0), vec![ApplyArg]].concat()), Some(2));
+        assert_eq!(info.binder_of(&body(1)), Some(4));
+        assert_eq!(info.binder_of(&body(2)), Some(0));
+    }
+
+    #[test]
+    fn test_scrutinee() {
+        // The parameters of the branches are not in scope in the scrutinee.
+        let info = resolved("λy. case y of { C(y) → y }");
+        assert_eq!(info.binder_of(&[LambdaBody, Scrutinee]), Some(0));
+        assert_eq!(
+            info.binder_of(&[LambdaBody, Branch(0), BranchBody]),
+            Some(1)
+        );
+        let info = resolved("case y of { C(y) → y }");
+        assert_eq!(info.free, vec![(vec![Scrutinee], "y".to_string())]);
+        assert_eq!(info.binder_of(&[Branch(0), BranchBody]), Some(0));
+    }
+
+    #[test]
+    fn test_free() {
+        let info = resolved("λx. case x of { C(z) → z; E() → C(z, x) }");
+        assert_eq!(
+            info.free,
+            vec![(
+                vec![LambdaBody, Branch(1), BranchBody, Argument(0)],
+                "z".to_string()
+            )]
+        );
+        assert_eq!(info.occurrences(0).count(), 2);
+    }
+}
@@ -0,0 +1,320 @@
+use crate::syntax::{
+    visit::{walk_fold, ExpFolder, ExpVisitor, Scope},
+    Branch, Exp, Variable,
+};
+
+use super::{instantiate, substitute, Evaluator, Status};
+
+/// How many reductions [`simplify`] performs at most, since reducing need not
+/// terminate.
+pub const DEFAULT_REDUCTION_LIMIT: usize = 10_000;
+
+/// Whether `x` occurs free in `exp`.
+struct Occurs<'a> {
+    x: &'a str,
+    found: bool,
+}
+
+impl ExpVisitor for Occurs<'_> {
+    fn visit_var(&mut self, x: &Variable, scope: &Scope) {
+        self.found |= x == self.x && !scope.is_bound(x);
+    }
+}
+
+fn occurs(x: &str, exp: &Exp) -> bool {
+    let mut occurs = Occurs { x, found: false };
+    occurs.visit(exp);
+    occurs.found
+}
+
+/// Whether `exp` has no free variables.
+struct Closed(bool);
+
+impl ExpVisitor for Closed {
+    fn visit_var(&mut self, x: &Variable, scope: &Scope) {
+        self.0 &= scope.is_bound(x);
+    }
+}
+
+fn is_closed(exp: &Exp) -> bool {
+    let mut closed = Closed(true);
+    closed.visit(exp);
+    closed.0
+}
+
+/// Whether evaluating `exp` gives it back as it is, so that substituting it
+/// is what evaluation does too.
+fn is_value(exp: &Exp) -> bool {
+    match exp {
+        Exp::Var(_) | Exp::Lambda(..) => true,
+        Exp::Const(_, arguments) => arguments.iter().all(is_value),
+        _ => false,
+    }
+}
+
+/// Whether `exp` is a value which can be substituted anywhere without being
+/// captured.
+fn is_closed_value(exp: &Exp) -> bool {
+    is_value(exp) && is_closed(exp)
+}
+
+/// One pass of simplification over the subterms of an expression, outermost
+/// first.
+struct Simplifier {
+    fuel: usize,
+    changed: bool,
+}
+
+impl Simplifier {
+    fn spend(&mut self) -> bool {
+        match self.fuel.checked_sub(1) {
+            Some(fuel) => {
+                self.fuel = fuel;
+                self.changed = true;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The value of the closed expression `exp`, if evaluating it finishes
+    /// within the steps left and gives something smaller.
+    fn evaluate(&mut self, exp: &Exp) -> Option<Exp> {
+        let mut evaluator = Evaluator::start(exp);
+        let status = evaluator.try_run(self.fuel);
+        self.fuel -= evaluator.steps();
+        match status {
+            Ok(Status::Done) => evaluator
+                .result()
+                .filter(|value| value.size() < exp.size())
+                .cloned(),
+            // Evaluation takes longer or fails, which is left to happen when
+            // the program runs.
+            Ok(Status::Running) | Err(_) => None,
+        }
+    }
+
+    /// `exp`, which has free variables, with a reduction done at the root
+    /// which evaluating it is sure to do once values are substituted for
+    /// them.
+    fn reduce(&mut self, exp: Exp) -> Exp {
+        match exp {
+            Exp::Apply(f, x) => match *f {
+                Exp::Lambda(y, body) if is_closed_value(&x) && self.spend() => {
+                    substitute(&body, &y, &x)
+                }
+                f => Exp::Apply(Box::new(f), x),
+            },
+            Exp::Case(e, branches) => match *e {
+                Exp::Const(constructor, arguments) => {
+                    // A branch with another arity makes evaluation fail, which
+                    // is left to happen.
+                    let taken = branches
+                        .iter()
+                        .position(|branch| branch.constructor == constructor)
+                        .filter(|&i| branches[i].parameters.len() == arguments.len());
+                    match taken {
+                        Some(i) if arguments.iter().all(is_closed_value) && self.spend() => {
+                            instantiate(&arguments, &branches[i])
+                        }
+                        Some(i) => {
+                            // The other branches can never be taken.
+                            self.changed |= branches.len() > 1;
+                            let taken = branches.into_iter().nth(i).unwrap();
+                            Exp::Case(Box::new(Exp::Const(constructor, arguments)), vec![taken])
+                        }
+                        None => Exp::Case(Box::new(Exp::Const(constructor, arguments)), branches),
+                    }
+                }
+                e => {
+                    // A branch after another with the same constructor can
+                    // never be taken.
+                    let count = branches.len();
+                    let mut reachable: Vec<Branch> = Vec::new();
+                    for branch in branches {
+                        if reachable
+                            .iter()
+                            .all(|b| b.constructor != branch.constructor)
+                        {
+                            reachable.push(branch);
+                        }
+                    }
+                    self.changed |= reachable.len() < count;
+                    Exp::Case(Box::new(e), reachable)
+                }
+            },
+            // Inlines `rec` definitions which do not refer to themselves.
+            Exp::Rec(x, body) if !occurs(&x, &body) => {
+                self.changed = true;
+                *body
+            }
+            exp => exp,
+        }
+    }
+}
+
+impl ExpFolder for Simplifier {
+    /// Replaces `exp` by its value if it is closed, and then simplifies its
+    /// subterms, under binders too: nothing can capture the variables of a
+    /// closed subterm, wherever it is. What is left with free variables is
+    /// reduced where it stands.
+    fn fold_exp(&mut self, exp: Exp, scope: &mut Scope) -> Exp {
+        let closed = is_closed(&exp);
+        let exp = match exp {
+            // A λ is a value already.
+            Exp::Lambda(..) => exp,
+            exp if closed => match self.evaluate(&exp) {
+                Some(value) => {
+                    self.changed = true;
+                    value
+                }
+                None => exp,
+            },
+            exp => exp,
+        };
+        let exp = walk_fold(self, exp, scope);
+        if closed {
+            exp
+        } else {
+            self.reduce(exp)
+        }
+    }
+}
+
+/// Simplifies `exp` where that cannot change what it evaluates to. Closed
+/// subterms, wherever they are, are replaced by their values when evaluating
+/// them is quick and gives something smaller. Elsewhere there is
+/// β-reduction of λ applied to closed values, `case` of a constructor applied
+/// to closed values, inlining of `rec` definitions which are not recursive and
+/// removal of branches which can never be taken.
+///
+/// If `exp` evaluates to a value built of constructors, the result evaluates
+/// to that value too. The functions and stuck terms evaluation gives back as
+/// they are written may come out simplified.
+pub fn simplify(exp: &Exp) -> Exp {
+    simplify_with_limit(exp, DEFAULT_REDUCTION_LIMIT)
+}
+
+/// Like [`simplify`], but stops after `reduction_limit` reductions, counting
+/// the evaluation steps taken on closed subterms.
+pub fn simplify_with_limit(exp: &Exp, reduction_limit: usize) -> Exp {
+    let mut simplifier = Simplifier {
+        fuel: reduction_limit,
+        changed: true,
+    };
+    let mut exp = exp.clone();
+    while simplifier.changed {
+        simplifier.changed = false;
+        exp = simplifier.fold(exp);
+    }
+    exp
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bootstrapping::{decompile, Context},
+        semantic::eval,
+        syntax::concrete,
+    };
+
+    fn simplified(code: &str) -> String {
+        concrete::format(&simplify(&concrete::parse(code).unwrap()))
+    }
+
+    /// Checks that `code` evaluates to the same value after simplifying.
+    fn assert_same_value(code: &str) {
+        let exp = concrete::parse(code).unwrap();
+        assert_eq!(eval(&simplify(&exp)), eval(&exp), "{}", code);
+    }
+
+    const ADD: &str = "rec add = λm. λn. case n of { Zero() → m; Suc(n) → Suc(add m n) }";
+
+    #[test]
+    fn test_simplify_redexes() {
+        assert_eq!(simplified("(λx. C(x, x)) D()"), "C(D(), D())");
+        assert_eq!(simplified("(λx. λy. x) D()"), "λy.D()");
+        assert_eq!(
+            simplified("case C(D()) of { C(x) → x; D() → E(); C(y) → y }"),
+            "D()"
+        );
+        assert_eq!(
+            simplified(&format!("({}) Suc(Zero()) Zero()", ADD)),
+            "Suc(Zero())"
+        );
+        assert_eq!(simplified("rec f = λx. x"), "λx.x");
+        assert_eq!(simplified("C(D() E(), (λx. x) F())"), "C(D() E(), F())");
+    }
+
+    #[test]
+    fn test_simplify_under_binders() {
+        assert_eq!(simplified("λz. (λx. C(x, x)) D()"), "λz.C(D(), D())");
+        assert_eq!(
+            simplified("λz. case C(f D()) of { D() → E(); C(x) → x; C(y) → y }"),
+            "λz.case C(f D()) of {\n  C(x) -> x\n}"
+        );
+        assert_eq!(
+            simplified("λz. case z of { C() → (λx. x) D() }"),
+            "λz.case z of {\n  C() -> D()\n}"
+        );
+        // Subterms with free variables are left alone.
+        assert_eq!(simplified("λz. (λx. C(x, x)) z"), "λz.((λx.C(x, x)) z)");
+        assert_eq!(
+            simplified("λz. (λx. λz. x) (λy. z)"),
+            "λz.((λx.λz.x) (λy.z))"
+        );
+        assert_same_value(&format!(
+            "(λf. f Zero()) (λn. ({}) n ((λx. x) Suc(n)))",
+            ADD
+        ));
+    }
+
+    #[test]
+    fn test_simplify_stuck() {
+        // Terms evaluation gets stuck on are kept, and only what they would
+        // evaluate later is reduced.
+        assert_eq!(simplified("C() ((λx. x) D())"), "C() D()");
+        assert_eq!(
+            simplified("case λy. y of { C() → (λx. x) D(); C() → E() }"),
+            "case λy.y of {\n  C() -> D();\n  C() -> E()\n}"
+        );
+        assert_eq!(
+            simplified("case C(D()) of { C() → D() }"),
+            "case C(D()) of {\n  C() -> D()\n}"
+        );
+        let omega = concrete::parse("(λx. x x) (λx. x x)").unwrap();
+        assert_eq!(simplify(&omega), omega);
+        assert_same_value("C(D() E(), (λx. x) F())");
+        assert_same_value("(λf. f D()) (λx. case x of { C() → E() })");
+    }
+
+    #[test]
+    fn test_simplify_same_value() {
+        for code in [
+            format!("({}) Suc(Zero()) ((λx. x) Suc(Zero()))", ADD),
+            "(λtwice. twice (λn. Suc(n)) Zero()) (λf. λx. f (f x))".to_string(),
+            format!(
+                "(λk. case k of {{ Zero() → ({}) k k; Suc(m) → m }}) (rec z = Zero())",
+                ADD
+            ),
+            format!("(λk. λm. ({}) k m) Suc(Zero()) Zero()", ADD),
+        ] {
+            assert_same_value(&code);
+        }
+    }
+
+    #[test]
+    fn test_simplify_expanded_interpreter() {
+        let interpreter =
+            concrete::parse(include_str!("../bootstrapping/eval_expanded.chi")).unwrap();
+        let program = concrete::parse("case (λx. C(x)) D() of { C(y) → y }").unwrap();
+        let exp = Exp::Apply(
+            Box::new(interpreter),
+            Box::new(decompile(&program, &mut Context::default())),
+        );
+        let simplified = simplify(&exp);
+        assert!(simplified.size() < exp.size());
+        assert_eq!(eval(&simplified), eval(&exp));
+    }
+}
@@ -0,0 +1,327 @@
+use std::{collections::HashMap, fmt};
+
+use serde::{Deserialize, Serialize};
+
+use crate::syntax::{
+    concrete, spine,
+    visit::{ExpVisitor, Scope},
+    Branch, Exp, Variable,
+};
+
+/// What a variable is known to be, relative to the parameters of the `rec`
+/// definition being checked.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Origin {
+    /// Exactly the `i`-th parameter.
+    Parameter(usize),
+    /// A strict subterm of the `i`-th parameter, bound by a `case` branch.
+    Subterm(usize),
+    Unknown,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Reason {
+    /// The recursive variable is used as a value instead of being called.
+    Escapes,
+    /// A recursive call has no argument which is a strict subterm of the
+    /// corresponding parameter.
+    NotSmaller { call: Exp },
+    /// Every call shrinks some argument, but no single argument shrinks in all
+    /// of them.
+    NoCommonArgument,
+}
+
+impl fmt::Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Reason::Escapes => write!(f, "it is used as a value, not only called"),
+            Reason::NotSmaller { call } => write!(
+                f,
+                "the recursive call `{}` is not on a strict subterm of a parameter",
+                concrete::format(call)
+            ),
+            Reason::NoCommonArgument => write!(
+                f,
+                "the recursive calls do not all decrease the same argument"
+            ),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Verdict {
+    /// The body never calls itself.
+    NoRecursiveCall,
+    /// Every recursive call is on a strict subterm of this (zero based) argument.
+    Structural {
+        decreasing_argument: usize,
+    },
+    NotCertified(Vec<Reason>),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecReport {
+    pub name: Variable,
+    pub verdict: Verdict,
+}
+
+impl RecReport {
+    pub fn is_certified(&self) -> bool {
+        !matches!(self.verdict, Verdict::NotCertified(_))
+    }
+}
+
+impl fmt::Display for RecReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.verdict {
+            Verdict::NoRecursiveCall => write!(f, "{}: terminates, no recursive call", self.name),
+            Verdict::Structural {
+                decreasing_argument,
+            } => write!(
+                f,
+                "{}: terminates, argument {} decreases structurally",
+                self.name,
+                decreasing_argument + 1
+            ),
+            Verdict::NotCertified(reasons) => {
+                write!(f, "{}: not certified", self.name)?;
+                for reason in reasons {
+                    write!(f, "\n  {}", reason)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+struct Checker<'a> {
+    name: &'a str,
+    escapes: bool,
+    // for each recursive call: the call itself and the arguments which shrink
+    calls: Vec<(Exp, Vec<usize>)>,
+}
+
+impl Checker<'_> {
+    fn visit(&mut self, exp: &Exp, env: &HashMap<Variable, Origin>) {
+        match exp {
+            Exp::Var(x) if x == self.name => self.escapes = true,
+            Exp::Var(_) => {}
+            Exp::Apply(_, _) => {
+                let (head, arguments) = spine(exp);
+                if matches!(head, Exp::Var(x) if x == self.name) {
+                    let decreasing = arguments
+                        .iter()
+                        .enumerate()
+                        .filter(|(i, argument)| {
+                            matches!(argument, Exp::Var(v) if env.get(v) == Some(&Origin::Subterm(*i)))
+                        })
+                        .map(|(i, _)| i)
+                        .collect();
+                    self.calls.push((exp.clone(), decreasing));
+                } else {
+                    self.visit(head, env);
+                }
+                for argument in arguments {
+                    self.visit(argument, env);
+                }
+            }
+            Exp::Lambda(x, body) | Exp::Rec(x, body) => {
+                if x != self.name {
+                    let mut env = env.clone();
+                    env.insert(x.clone(), Origin::Unknown);
+                    self.visit(body, &env);
+                }
+            }
+            Exp::Case(scrutinee, branches) => {
+                self.visit(scrutinee, env);
+                let origin = match scrutinee.as_ref() {
+                    Exp::Var(v) => match env.get(v) {
+                        Some(Origin::Parameter(i)) | Some(Origin::Subterm(i)) => {
+                            Origin::Subterm(*i)
+                        }
+                        _ => Origin::Unknown,
+                    },
+                    _ => Origin::Unknown,
+                };
+                for branch in branches {
+                    self.visit_branch(branch, origin, env);
+                }
+            }
+            Exp::Const(_, arguments) => {
+                for argument in arguments {
+                    self.visit(argument, env);
+                }
+            }
+        }
+    }
+
+    fn visit_branch(&mut self, branch: &Branch, origin: Origin, env: &HashMap<Variable, Origin>) {
+        if branch.parameters.iter().any(|x| x == self.name) {
+            return;
+        }
+        let mut env = env.clone();
+        for parameter in &branch.parameters {
+            env.insert(parameter.clone(), origin);
+        }
+        self.visit(&branch.expression, &env);
+    }
+
+    fn verdict(self) -> Verdict {
+        let mut reasons = vec![];
+        if self.escapes {
+            reasons.push(Reason::Escapes);
+        }
+        for (call, decreasing) in &self.calls {
+            if decreasing.is_empty() {
+                reasons.push(Reason::NotSmaller { call: call.clone() });
+            }
+        }
+        if !reasons.is_empty() {
+            return Verdict::NotCertified(reasons);
+        }
+        let mut calls = self.calls.iter();
+        let Some((_, first)) = calls.next() else {
+            return Verdict::NoRecursiveCall;
+        };
+        let common = first.iter().find(|i| {
+            self.calls
+                .iter()
+                .all(|(_, decreasing)| decreasing.contains(i))
+        });
+        match common {
+            Some(&decreasing_argument) => Verdict::Structural {
+                decreasing_argument,
+            },
+            None => Verdict::NotCertified(vec![Reason::NoCommonArgument]),
+        }
+    }
+}
+
+fn check_rec(name: &Variable, body: &Exp) -> RecReport {
+    let mut env = HashMap::new();
+    let mut body = body;
+    let mut index = 0;
+    while let Exp::Lambda(x, inner) = body {
+        if x == name {
+            break;
+        }
+        env.insert(x.clone(), Origin::Parameter(index));
+        index += 1;
+        body = inner;
+    }
+    let mut checker = Checker {
+        name,
+        escapes: false,
+        calls: vec![],
+    };
+    checker.visit(body, &env);
+    RecReport {
+        name: name.clone(),
+        verdict: checker.verdict(),
+    }
+}
+
+/// Checks each `rec` definition it visits, outermost first.
+struct Reports(Vec<RecReport>);
+
+impl ExpVisitor for Reports {
+    fn visit_rec(&mut self, x: &Variable, body: &Exp, scope: &mut Scope) {
+        self.0.push(check_rec(x, body));
+        scope.with(Some(x.clone()), |scope| self.visit_exp(body, scope))
+    }
+}
+
+/// Conservatively checks every `rec` definition in `exp` for structural
+/// recursion: each recursive call must pass, at a fixed argument position, a
+/// variable bound by a `case` branch on that very parameter (or on a subterm
+/// of it). Definitions which are not certified come with the reasons why.
+pub fn check_termination(exp: &Exp) -> Vec<RecReport> {
+    let mut reports = Reports(vec![]);
+    reports.visit(exp);
+    reports.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn verdict_of(code: &str) -> Verdict {
+        let term = concrete::parse(code).unwrap();
+        check_termination(&term).remove(0).verdict
+    }
+
+    #[test]
+    fn test_structural() {
+        assert_eq!(
+            verdict_of(
+                "rec add = λm.λn. case n of {
+                    Zero() → m;
+                    Suc(n) → Suc(add m n)
+                }"
+            ),
+            Verdict::Structural {
+                decreasing_argument: 1
+            }
+        );
+        assert_eq!(
+            verdict_of(include_str!("../bootstrapping/equal.chi")),
+            Verdict::Structural {
+                decreasing_argument: 0
+            }
+        );
+        assert_eq!(
+            verdict_of(include_str!("../bootstrapping/lookup.chi")),
+            Verdict::Structural {
+                decreasing_argument: 1
+            }
+        );
+        assert_eq!(
+            verdict_of(include_str!("../bootstrapping/substs.chi")),
+            Verdict::Structural {
+                decreasing_argument: 0
+            }
+        );
+        assert_eq!(verdict_of("rec f = λx. x"), Verdict::NoRecursiveCall);
+    }
+
+    #[test]
+    fn test_not_certified() {
+        assert_eq!(
+            verdict_of("rec x = x"),
+            Verdict::NotCertified(vec![Reason::Escapes])
+        );
+        assert_eq!(
+            verdict_of("rec f = λx. f x"),
+            Verdict::NotCertified(vec![Reason::NotSmaller {
+                call: concrete::parse("f x").unwrap()
+            }])
+        );
+        assert_eq!(
+            verdict_of(
+                "rec f = λx.λy. case x of {
+                    Suc(x) → f x y;
+                    Zero() → case y of { Suc(y) → f x y }
+                }"
+            ),
+            Verdict::NotCertified(vec![Reason::NoCommonArgument])
+        );
+        assert!(!check_termination(
+            &concrete::parse(include_str!("../bootstrapping/eval.chi")).unwrap()
+        )[0]
+        .is_certified());
+    }
+
+    #[test]
+    fn test_shadowing() {
+        // the inner `n` is bound by a lambda, not by the case branch
+        assert!(matches!(
+            verdict_of("rec f = λn. case n of { Suc(m) → (λn. f n) m }"),
+            Verdict::NotCertified(_)
+        ));
+        // calls to a shadowing `f` are not recursive calls
+        assert_eq!(
+            verdict_of("rec f = λx. (λf. f x) (λy. y)"),
+            Verdict::NoRecursiveCall
+        );
+    }
+}
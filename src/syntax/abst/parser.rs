@@ -195,12 +195,9 @@ fn parse_exp(code: &str) -> IResult<&str, Exp> {
 }
 
 pub fn parse(code: &str) -> Result<Exp, ()> {
-    if let Ok((rest, exp)) = parse_exp(code)
-        && rest.trim() == ""
-    {
-        Ok(exp)
-    } else {
-        Err(())
+    match parse_exp(code) {
+        Ok((rest, exp)) if rest.trim() == "" => Ok(exp),
+        _ => Err(()),
     }
 }
 
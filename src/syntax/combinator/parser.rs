@@ -0,0 +1,127 @@
+use nom::{
+    branch::alt,
+    character::complete::{char, multispace0},
+    combinator::map,
+    multi::many0,
+    sequence::{delimited, pair, preceded},
+    IResult,
+};
+
+use crate::syntax::{
+    apply_all, concrete::SyntaxError, constructor, finish, variable, variables, Exp, Variable,
+};
+
+fn atom(code: &str) -> IResult<&str, Exp> {
+    preceded(
+        multispace0,
+        alt((
+            map(constructor, |c| Exp::Const(c, vec![])),
+            map(variable, Exp::Var),
+            delimited(char('('), application, preceded(multispace0, char(')'))),
+        )),
+    )(code)
+}
+
+fn application(code: &str) -> IResult<&str, Exp> {
+    map(pair(atom, many0(atom)), |(f, arguments)| {
+        apply_all(f, arguments)
+    })(code)
+}
+
+/// Parses combinatory logic notation such as `S K K x`. Combinators are
+/// capitalised and become nullary constructors, other names become variables.
+pub fn parse(code: &str) -> Result<Exp, SyntaxError> {
+    finish(code, application(code))
+}
+
+fn definition(combinator: &str, names: &[Variable; 3]) -> Option<Exp> {
+    let var = |i: usize| Exp::Var(names[i].clone());
+    let apply = |f: Exp, x: Exp| Exp::Apply(Box::new(f), Box::new(x));
+    let lambda = |i: usize, body: Exp| Exp::Lambda(names[i].clone(), Box::new(body));
+    let exp = match combinator {
+        "I" => lambda(0, var(0)),
+        "K" => lambda(0, lambda(1, var(0))),
+        "S" => lambda(
+            0,
+            lambda(
+                1,
+                lambda(2, apply(apply(var(0), var(2)), apply(var(1), var(2)))),
+            ),
+        ),
+        "B" => lambda(
+            0,
+            lambda(1, lambda(2, apply(var(0), apply(var(1), var(2))))),
+        ),
+        "C" => lambda(
+            0,
+            lambda(1, lambda(2, apply(apply(var(0), var(2)), var(1)))),
+        ),
+        "W" => lambda(0, lambda(1, apply(apply(var(0), var(1)), var(1)))),
+        _ => return None,
+    };
+    Some(exp)
+}
+
+fn expand_with(exp: &Exp, names: &[Variable; 3]) -> Exp {
+    match exp {
+        Exp::Const(c, arguments) if arguments.is_empty() => {
+            definition(c, names).unwrap_or_else(|| exp.clone())
+        }
+        Exp::Apply(f, x) => Exp::Apply(
+            Box::new(expand_with(f, names)),
+            Box::new(expand_with(x, names)),
+        ),
+        _ => exp.clone(),
+    }
+}
+
+/// Replaces the combinators `S`, `K`, `I`, `B`, `C` and `W` by their
+/// definitions as λ-terms so that the result can be run with
+/// [`crate::semantic::eval`]. Other constructors are kept as they are.
+pub fn expand(exp: &Exp) -> Exp {
+    let used = variables(exp);
+    // the bound names must not capture the free variables of the term
+    let names = ["x", "y", "z"].map(|base| {
+        let mut name = base.to_string();
+        while used.contains(&name) {
+            name.push('\'');
+        }
+        name
+    });
+    expand_with(exp, &names)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{semantic::eval, syntax::concrete};
+
+    #[test]
+    fn test_parse() {
+        let exp = parse("S K K x").unwrap();
+        assert_eq!(exp, concrete::parse("S() K() K() x").unwrap());
+
+        let exp = parse("S (K x) (S K)").unwrap();
+        assert_eq!(exp, concrete::parse("S() (K() x) (S() K())").unwrap());
+
+        assert_eq!(parse("S (K"), Err(SyntaxError { offset: 2 }));
+        assert_eq!(parse("S K )"), Err(SyntaxError { offset: 4 }));
+    }
+
+    #[test]
+    fn test_expand() {
+        let exp = expand(&parse("S K K x").unwrap());
+        assert_eq!(eval(&exp), Exp::Var("x".to_string()));
+
+        // `y` is free and must not be captured by the definition of S
+        let exp = expand(&parse("S y K I").unwrap());
+        assert_eq!(
+            exp,
+            concrete::parse("(λx.λy'.λz.x z (y' z)) y (λx.λy'.x) (λx.x)").unwrap()
+        );
+
+        // constructors other than the combinators are kept as χ values
+        let exp = expand(&parse("S K S Zero").unwrap());
+        assert_eq!(eval(&exp), concrete::parse("Zero()").unwrap());
+    }
+}
@@ -1,5 +0,0 @@
-mod formatter;
-mod parser;
-
-pub use formatter::format;
-pub use parser::parse;
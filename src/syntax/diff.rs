@@ -0,0 +1,316 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::{concrete, Exp, Path, Step, Variable};
+
+/// Subterms at the same path which differ, though every subterm around them
+/// is alike.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Difference {
+    pub path: Path,
+    pub left: Exp,
+    pub right: Exp,
+}
+
+/// The smallest subterms in which two expressions differ, outermost first.
+/// Displays as an inline diff of the subterms in concrete syntax.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Diff {
+    pub differences: Vec<Difference>,
+}
+
+/// The steps to a child and the binders put around it on the left and on the
+/// right.
+type Child = (Path, Vec<Variable>, Vec<Variable>);
+
+/// Compares two expressions, keeping the binders around the subterms
+/// compared, innermost last, on each side.
+#[derive(Default)]
+struct Differ {
+    modulo_alpha: bool,
+    left: Vec<Variable>,
+    right: Vec<Variable>,
+    path: Path,
+    differences: Vec<Difference>,
+}
+
+impl Differ {
+    fn same_binders(&self, left: &[Variable], right: &[Variable]) -> bool {
+        left.len() == right.len() && (self.modulo_alpha || left == right)
+    }
+
+    /// Whether two variables refer to the same thing: the same free variable,
+    /// or, modulo α, binders equally far out.
+    fn same_variable(&self, x: &Variable, y: &Variable) -> bool {
+        if !self.modulo_alpha {
+            return x == y;
+        }
+        let left = self.left.iter().rev().position(|binder| binder == x);
+        let right = self.right.iter().rev().position(|binder| binder == y);
+        match (left, right) {
+            (None, None) => x == y,
+            (left, right) => left == right,
+        }
+    }
+
+    /// The children of `left` and `right`, or `None` if they differ in
+    /// anything but their children.
+    fn binders(&self, left: &Exp, right: &Exp) -> Option<Vec<Child>> {
+        let unbound = |step: Step| (vec![step], vec![], vec![]);
+        match (left, right) {
+            (Exp::Var(x), Exp::Var(y)) if self.same_variable(x, y) => Some(vec![]),
+            (Exp::Apply(..), Exp::Apply(..)) => {
+                Some(vec![unbound(Step::ApplyFun), unbound(Step::ApplyArg)])
+            }
+            (Exp::Lambda(x, _), Exp::Lambda(y, _))
+                if self.same_binders(std::slice::from_ref(x), std::slice::from_ref(y)) =>
+            {
+                Some(vec![(
+                    vec![Step::LambdaBody],
+                    vec![x.clone()],
+                    vec![y.clone()],
+                )])
+            }
+            (Exp::Rec(x, _), Exp::Rec(y, _))
+                if self.same_binders(std::slice::from_ref(x), std::slice::from_ref(y)) =>
+            {
+                Some(vec![(
+                    vec![Step::RecBody],
+                    vec![x.clone()],
+                    vec![y.clone()],
+                )])
+            }
+            (Exp::Case(_, left), Exp::Case(_, right)) if left.len() == right.len() => {
+                let mut binders = vec![unbound(Step::Scrutinee)];
+                for (i, (left, right)) in left.iter().zip(right).enumerate() {
+                    if left.constructor != right.constructor
+                        || !self.same_binders(&left.parameters, &right.parameters)
+                    {
+                        return None;
+                    }
+                    binders.push((
+                        vec![Step::Branch(i), Step::BranchBody],
+                        left.parameters.clone(),
+                        right.parameters.clone(),
+                    ));
+                }
+                Some(binders)
+            }
+            (Exp::Const(c, left), Exp::Const(d, right)) if c == d && left.len() == right.len() => {
+                Some(
+                    (0..left.len())
+                        .map(|i| unbound(Step::Argument(i)))
+                        .collect(),
+                )
+            }
+            _ => None,
+        }
+    }
+
+    fn compare(&mut self, left: &Exp, right: &Exp) {
+        let binders = match self.binders(left, right) {
+            Some(binders) => binders,
+            None => {
+                self.differences.push(Difference {
+                    path: self.path.clone(),
+                    left: left.clone(),
+                    right: right.clone(),
+                });
+                return;
+            }
+        };
+        for (steps, left_binders, right_binders) in binders {
+            let depth = (self.left.len(), self.right.len(), self.path.len());
+            self.left.extend(left_binders);
+            self.right.extend(right_binders);
+            self.path.extend_from_slice(&steps);
+            self.compare(left.get(&steps).unwrap(), right.get(&steps).unwrap());
+            self.path.truncate(depth.2);
+            self.left.truncate(depth.0);
+            self.right.truncate(depth.1);
+        }
+    }
+}
+
+fn differ(left: &Exp, right: &Exp, modulo_alpha: bool) -> Diff {
+    let mut differ = Differ {
+        modulo_alpha,
+        ..Differ::default()
+    };
+    differ.compare(left, right);
+    Diff {
+        differences: differ.differences,
+    }
+}
+
+/// Where `left` and `right` differ, binders included.
+pub fn diff(left: &Exp, right: &Exp) -> Diff {
+    differ(left, right, false)
+}
+
+/// Where `left` and `right` differ other than in the names of binders.
+pub fn diff_modulo_alpha(left: &Exp, right: &Exp) -> Diff {
+    differ(left, right, true)
+}
+
+fn location(path: &[Step]) -> String {
+    if path.is_empty() {
+        "the root".to_string()
+    } else {
+        path.iter()
+            .map(|step| format!("{:?}", step))
+            .collect::<Vec<_>>()
+            .join(".")
+    }
+}
+
+impl Diff {
+    pub fn is_empty(&self) -> bool {
+        self.differences.is_empty()
+    }
+
+    /// The differing subterms in two columns, left and right.
+    pub fn side_by_side(&self) -> String {
+        let mut result = String::new();
+        for difference in &self.differences {
+            let left = concrete::format(&difference.left);
+            let right = concrete::format(&difference.right);
+            let width = left.lines().map(|line| line.chars().count()).max();
+            let (left, right): (Vec<_>, Vec<_>) = (left.lines().collect(), right.lines().collect());
+            result.push_str(&format!("at {}:\n", location(&difference.path)));
+            for i in 0..left.len().max(right.len()) {
+                let line = format!(
+                    "{:width$} | {}",
+                    left.get(i).unwrap_or(&""),
+                    right.get(i).unwrap_or(&""),
+                    width = width.unwrap_or(0)
+                );
+                result.push_str(line.trim_end());
+                result.push('\n');
+            }
+        }
+        result
+    }
+}
+
+impl fmt::Display for Diff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for difference in &self.differences {
+            writeln!(f, "at {}:", location(&difference.path))?;
+            for line in concrete::format(&difference.left).lines() {
+                writeln!(f, "- {}", line)?;
+            }
+            for line in concrete::format(&difference.right).lines() {
+                writeln!(f, "+ {}", line)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::syntax::Step::*;
+
+    fn paths(left: &str, right: &str) -> Vec<Path> {
+        let left = concrete::parse(left).unwrap();
+        let right = concrete::parse(right).unwrap();
+        diff(&left, &right)
+            .differences
+            .into_iter()
+            .map(|difference| difference.path)
+            .collect()
+    }
+
+    #[test]
+    fn test_diff() {
+        assert_eq!(
+            paths("λx. C(x, D(), f x)", "λx. C(x, E(), f y)"),
+            vec![
+                vec![LambdaBody, Argument(1)],
+                vec![LambdaBody, Argument(2), ApplyArg]
+            ]
+        );
+        let exp = concrete::parse("λx. C(x, D(), f x)").unwrap();
+        assert!(diff(&exp, &exp).is_empty());
+    }
+
+    #[test]
+    fn test_display() {
+        let left = concrete::parse("λx. C(x, D(), f x)").unwrap();
+        let right = concrete::parse("λx. C(x, E(), f y)").unwrap();
+        let differences = diff(&left, &right);
+        assert_eq!(
+            differences.to_string(),
+            "at LambdaBody.Argument(1):\n- D()\n+ E()\nat LambdaBody.Argument(2).ApplyArg:\n- x\n+ y\n"
+        );
+        assert_eq!(
+            differences.side_by_side(),
+            "at LambdaBody.Argument(1):\nD() | E()\nat LambdaBody.Argument(2).ApplyArg:\nx | y\n"
+        );
+    }
+
+    #[test]
+    fn test_diff_case() {
+        assert_eq!(
+            paths(
+                "case x of { C(y) → y; D() → E() }",
+                "case z of { C(y) → y; D() → F() }"
+            ),
+            vec![vec![Scrutinee], vec![Branch(1), BranchBody]]
+        );
+        // A case expression with other branches differs as a whole.
+        assert_eq!(
+            paths(
+                "case x of { C() → D() }",
+                "case x of { C() → D(); E() → F() }"
+            ),
+            vec![Path::new()]
+        );
+        assert_eq!(
+            paths("case x of { C() → D() }", "case x of { E() → D() }"),
+            vec![Path::new()]
+        );
+        assert_eq!(
+            paths("case x of { C(y) → D() }", "case x of { C() → D() }"),
+            vec![Path::new()]
+        );
+    }
+
+    #[test]
+    fn test_diff_binders() {
+        let left = "λx. case x of { C(y) → λz. y z }";
+        let right = "λa. case a of { C(b) → λz. b z }";
+        assert_eq!(paths(left, right), vec![Path::new()]);
+        assert_eq!(
+            paths("case x of { C(y) → y }", "case x of { C(z) → z }"),
+            vec![Path::new()]
+        );
+    }
+
+    #[test]
+    fn test_diff_modulo_alpha() {
+        let left = concrete::parse("λx. case x of { C(y) → λz. y z }").unwrap();
+        let right = concrete::parse("λa. case a of { C(b) → λz. b z }").unwrap();
+        assert!(diff_modulo_alpha(&left, &right).is_empty());
+
+        let left = concrete::parse("λx. λy. x").unwrap();
+        let right = concrete::parse("λy. λx. x").unwrap();
+        let differences = diff_modulo_alpha(&left, &right);
+        assert_eq!(
+            differences.differences[0].path,
+            vec![LambdaBody, LambdaBody]
+        );
+        let free = concrete::parse("λy. λx. z").unwrap();
+        assert_eq!(diff_modulo_alpha(&free, &right).differences.len(), 1);
+
+        // Modulo α, branches still need the same constructors and arities.
+        let left = concrete::parse("case x of { C(y) → y }").unwrap();
+        let right = concrete::parse("case x of { D(z) → z }").unwrap();
+        assert_eq!(diff_modulo_alpha(&left, &right).differences.len(), 1);
+        let right = concrete::parse("case x of { C(z) → z }").unwrap();
+        assert!(diff_modulo_alpha(&left, &right).is_empty());
+    }
+}
@@ -0,0 +1,166 @@
+use nom::{
+    branch::alt,
+    bytes::complete::tag,
+    character::complete::{char, digit1, multispace0},
+    combinator::{map, opt},
+    error::{Error, ErrorKind},
+    multi::{many0, many1},
+    sequence::{delimited, preceded, tuple},
+    IResult,
+};
+
+use crate::syntax::{apply_all, concrete::SyntaxError, finish, lambda_all, variable, Exp};
+
+fn lambda_sign(code: &str) -> IResult<&str, &str> {
+    preceded(multispace0, alt((tag(r#"\"#), tag("λ"), tag("𝜆"))))(code)
+}
+
+fn abstraction(code: &str) -> IResult<&str, Exp> {
+    map(
+        tuple((
+            lambda_sign,
+            many1(preceded(multispace0, variable)),
+            preceded(multispace0, char('.')),
+            term,
+        )),
+        |(_, variables, _, body)| lambda_all(variables.into_iter(), body),
+    )(code)
+}
+
+fn atom(code: &str) -> IResult<&str, Exp> {
+    preceded(
+        multispace0,
+        alt((
+            map(variable, Exp::Var),
+            delimited(char('('), term, preceded(multispace0, char(')'))),
+        )),
+    )(code)
+}
+
+// an abstraction may end an application without parentheses, as in `f λx. x`
+fn application(code: &str) -> IResult<&str, Exp> {
+    map(
+        tuple((atom, many0(atom), opt(abstraction))),
+        |(f, mut arguments, last)| {
+            arguments.extend(last);
+            apply_all(f, arguments)
+        },
+    )(code)
+}
+
+fn term(code: &str) -> IResult<&str, Exp> {
+    alt((abstraction, application))(code)
+}
+
+/// Parses untyped λ-calculus notation, where `λx y. e` abbreviates `λx. λy. e`
+/// and application is left associative.
+pub fn parse(code: &str) -> Result<Exp, SyntaxError> {
+    finish(code, term(code))
+}
+
+// the nameless term is parsed `depth` binders deep, naming the binder at depth
+// `d` `xd` as soon as it is parsed
+fn nameless_abstraction(depth: usize, code: &str) -> IResult<&str, Exp> {
+    map(
+        tuple(
+            (lambda_sign, opt(preceded(multispace0, char('.'))), |code| {
+                nameless_term(depth + 1, code)
+            }),
+        ),
+        |(_, _, body)| Exp::Lambda(format!("x{}", depth), Box::new(body)),
+    )(code)
+}
+
+// an index which refers to no binder is a failure rather than the end of an
+// application, so that the error is reported where it is
+fn index(depth: usize, code: &str) -> IResult<&str, Exp> {
+    let (rest, index) = digit1(code)?;
+    match index.parse::<usize>() {
+        Ok(index) if 0 < index && index <= depth => {
+            Ok((rest, Exp::Var(format!("x{}", depth - index))))
+        }
+        _ => Err(nom::Err::Failure(Error::new(code, ErrorKind::Verify))),
+    }
+}
+
+fn nameless_atom(depth: usize, code: &str) -> IResult<&str, Exp> {
+    preceded(
+        multispace0,
+        alt((
+            |code| index(depth, code),
+            delimited(
+                char('('),
+                |code| nameless_term(depth, code),
+                preceded(multispace0, char(')')),
+            ),
+        )),
+    )(code)
+}
+
+fn nameless_application(depth: usize, code: &str) -> IResult<&str, Exp> {
+    map(
+        tuple((
+            |code| nameless_atom(depth, code),
+            many0(|code| nameless_atom(depth, code)),
+            opt(|code| nameless_abstraction(depth, code)),
+        )),
+        |(f, mut arguments, last)| {
+            arguments.extend(last);
+            apply_all(f, arguments)
+        },
+    )(code)
+}
+
+fn nameless_term(depth: usize, code: &str) -> IResult<&str, Exp> {
+    alt((
+        |code| nameless_abstraction(depth, code),
+        |code| nameless_application(depth, code),
+    ))(code)
+}
+
+/// Parses a closed λ-term written with de Bruijn indices, counting from 1 for
+/// the nearest binder, e.g. `λ λ 2` for `λx0. λx1. x0`. The binder at depth
+/// `d` is named `xd`.
+pub fn parse_de_bruijn(code: &str) -> Result<Exp, SyntaxError> {
+    finish(code, nameless_term(0, code))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::syntax::concrete;
+
+    #[test]
+    fn test_parse() {
+        let exp = parse(r#"λx y. x"#).unwrap();
+        assert_eq!(exp, concrete::parse("λx.λy.x").unwrap());
+
+        let exp = parse(r#"\f. (\x. f (x x)) (\x. f (x x))"#).unwrap();
+        assert_eq!(
+            exp,
+            concrete::parse("λf.(λx.f (x x)) (λx.f (x x))").unwrap()
+        );
+
+        let exp = parse(r#"f g λx. x"#).unwrap();
+        assert_eq!(exp, concrete::parse("f g (λx.x)").unwrap());
+
+        assert_eq!(parse("λ. x"), Err(SyntaxError { offset: 0 }));
+        assert_eq!(parse("f (x"), Err(SyntaxError { offset: 2 }));
+    }
+
+    #[test]
+    fn test_parse_de_bruijn() {
+        let exp = parse_de_bruijn("λ λ 2").unwrap();
+        assert_eq!(exp, concrete::parse("λx0.λx1.x0").unwrap());
+
+        let exp = parse_de_bruijn("λ. (λ. 1 1) (λ. 2 1)").unwrap();
+        assert_eq!(exp, concrete::parse("λx0.(λx1.x1 x1) (λx1.x0 x1)").unwrap());
+
+        assert_eq!(parse_de_bruijn("λ 2"), Err(SyntaxError { offset: 3 }));
+        assert_eq!(
+            parse_de_bruijn("λ λ 1 (2 3)"),
+            Err(SyntaxError { offset: 11 })
+        );
+        assert_eq!(parse_de_bruijn("λ 0"), Err(SyntaxError { offset: 3 }));
+    }
+}
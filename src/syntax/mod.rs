@@ -1,93 +1,108 @@
-use nom::{
-    branch::alt,
-    character::complete::{alphanumeric1, one_of},
-    combinator::{map, recognize},
-    multi::many0,
-    sequence::pair,
-    IResult,
-};
-use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use nom::IResult;
 
 pub mod abst;
-pub mod concrete;
+pub mod combinator;
+pub mod diff;
+pub mod lambda;
+mod token;
+pub mod visit;
+
+pub use chi_syntax::{concrete, is_variable_name, Branch, Constructor, Exp, Path, Step, Variable};
+use chi_syntax::{constructor, variable};
+pub use token::{tokenize, TokenKind};
+use visit::{ExpVisitor, Scope};
+
+/// The notations χ programs are written in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum Syntax {
+    Concrete,
+    Abstract,
+}
 
-pub type Variable = String;
-pub type Constructor = String;
+/// The words abstract syntax starts a term with.
+const ABSTRACT_KEYWORDS: [&str; 6] = ["var", "const", "apply", "lambda", "case", "rec"];
 
-#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Hash)]
-pub struct Branch {
-    pub constructor: Constructor,
-    pub parameters: Vec<Variable>,
-    pub expression: Box<Exp>,
+/// Whether `exp` is an application of a variable named after a keyword of
+/// abstract syntax.
+fn starts_with_keyword(exp: &Exp) -> bool {
+    matches!(spine(exp).0, Exp::Var(x) if ABSTRACT_KEYWORDS.contains(&x.as_str()))
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
-pub enum Exp {
-    Apply(Box<Exp>, Box<Exp>),
-    Lambda(String, Box<Exp>),
-    Case(Box<Exp>, Vec<Branch>),
-    Rec(Variable, Box<Exp>),
-    Var(Variable),
-    Const(String, Vec<Exp>),
+/// Parses `code` in concrete syntax, or else in abstract syntax, and tells
+/// which it was written in. Most abstract syntax, such as `lambda x (var x)`,
+/// also reads as a concrete application of a variable named after a keyword
+/// of abstract syntax, and is taken as abstract syntax if it is valid.
+pub fn parse(code: &str) -> Result<(Exp, Syntax), ()> {
+    match concrete::parse(code) {
+        Ok(exp) if !starts_with_keyword(&exp) => Ok((exp, Syntax::Concrete)),
+        concrete => abst::parse(code)
+            .map(|exp| (exp, Syntax::Abstract))
+            .or_else(|_| concrete.map(|exp| (exp, Syntax::Concrete))),
+    }
 }
 
-fn constructor(input: &str) -> IResult<&str, Constructor> {
-    let first = one_of("ABCDEFGHIJKLMNOPQRSTUVWXYZ");
-    let rest = recognize(many0(alt((alphanumeric1, recognize(one_of("_-'"))))));
-
-    map(pair(first, rest), |(f, r): (char, &str)| {
-        format!("{}{}", f, r)
-    })(input)
+/// The result of a parser for the whole of `code`, or where it stopped: at
+/// what is left over, or at the input the parser failed on.
+fn finish(code: &str, result: IResult<&str, Exp>) -> Result<Exp, concrete::SyntaxError> {
+    let rest = match result {
+        Ok((rest, exp)) if rest.trim().is_empty() => return Ok(exp),
+        Ok((rest, _)) => rest.trim_start(),
+        Err(nom::Err::Error(error) | nom::Err::Failure(error)) => error.input,
+        Err(nom::Err::Incomplete(_)) => "",
+    };
+    Err(concrete::SyntaxError {
+        offset: code.len() - rest.len(),
+    })
 }
 
-fn variable(input: &str) -> IResult<&str, Variable> {
-    let first = one_of("abcdefghijklmnopqrstuvwxyz_");
-    let rest = recognize(many0(alt((alphanumeric1, recognize(one_of("_-'"))))));
-
-    map(pair(first, rest), |(f, r): (char, &str)| {
-        format!("{}{}", f, r)
-    })(input)
+/// `f` applied to `arguments`, the first innermost.
+pub(crate) fn apply_all(f: Exp, arguments: impl IntoIterator<Item = Exp>) -> Exp {
+    arguments
+        .into_iter()
+        .fold(f, |f, x| Exp::Apply(Box::new(f), Box::new(x)))
 }
 
-#[cfg(test)]
-mod tests {
-    use std::assert_matches::assert_matches;
-
-    use super::*;
-
-    #[test]
-    fn test_parse_constructor() {
-        let code = "Foo";
-        let (_, constr) = constructor(code).unwrap();
-        assert_eq!(constr, "Foo");
-
-        let code = "Foo_bar";
-        let (_, constr) = constructor(code).unwrap();
-        assert_eq!(constr, "Foo_bar");
-
-        let code = "Foo'bar";
-        let (_, constr) = constructor(code).unwrap();
-        assert_eq!(constr, "Foo'bar");
+/// `body` under a λ for each of `parameters`, the first outermost.
+pub(crate) fn lambda_all(parameters: impl DoubleEndedIterator<Item = Variable>, body: Exp) -> Exp {
+    parameters
+        .rev()
+        .fold(body, |body, x| Exp::Lambda(x, Box::new(body)))
+}
 
-        let code = "foo";
-        assert_matches!(constructor(code), Err(_));
+/// The inverse of [`apply_all`]: the function an application applies in the
+/// end, and its arguments in order.
+pub(crate) fn spine(exp: &Exp) -> (&Exp, Vec<&Exp>) {
+    let mut arguments = vec![];
+    let mut head = exp;
+    while let Exp::Apply(f, x) = head {
+        arguments.push(x.as_ref());
+        head = f;
     }
+    arguments.reverse();
+    (head, arguments)
+}
 
-    #[test]
-    fn test_parse_variable() {
-        let code = "foo";
-        let (_, var) = variable(code).unwrap();
-        assert_eq!(var, "foo");
-
-        let code = "foo_bar";
-        let (_, var) = variable(code).unwrap();
-        assert_eq!(var, "foo_bar");
+/// Every variable name in an expression, bound or free.
+#[derive(Default)]
+struct Variables(HashSet<Variable>);
 
-        let code = "foo'bar";
-        let (_, var) = variable(code).unwrap();
-        assert_eq!(var, "foo'bar");
+impl ExpVisitor for Variables {
+    fn visit_var(&mut self, x: &Variable, _scope: &Scope) {
+        self.0.insert(x.clone());
+    }
 
-        let code = "Foo";
-        assert_matches!(variable(code), Err(_));
+    fn visit_binder(&mut self, x: &Variable, _scope: &Scope) {
+        self.0.insert(x.clone());
     }
 }
+
+/// Every variable name in `exp`, bound or free, so that names not among them
+/// can be bound anywhere in `exp` without capturing anything.
+pub(crate) fn variables(exp: &Exp) -> HashSet<Variable> {
+    let mut variables = Variables::default();
+    variables.visit(exp);
+    variables.0
+}
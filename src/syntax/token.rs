@@ -0,0 +1,265 @@
+use serde::{Deserialize, Serialize};
+
+use crate::syntax::{abst, concrete, Exp, Variable};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TokenKind {
+    Keyword,
+    Constructor,
+    /// A variable where it is bound by a λ, `rec` or case branch.
+    Binder,
+    BoundVariable,
+    FreeVariable,
+    /// A variable in code which does not parse, so whether it is bound is
+    /// unknown.
+    Variable,
+    Comment,
+}
+
+const CONCRETE_KEYWORDS: [&str; 3] = ["case", "of", "rec"];
+const ABSTRACT_KEYWORDS: [&str; 9] = [
+    "apply", "lambda", "case", "rec", "var", "const", "branch", "cons", "nil",
+];
+
+fn is_word_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "_-'".contains(c)
+}
+
+/// Splits `code` into comments and words, the latter including the λ
+/// symbols, the same way the parsers do.
+fn lex(code: &str) -> Vec<(usize, usize, Option<TokenKind>)> {
+    let mut tokens = vec![];
+    let mut index = 0;
+    while let Some(c) = code[index..].chars().next() {
+        let rest = &code[index..];
+        let end = if rest.starts_with("--") {
+            let end = index + rest.find('\n').unwrap_or(rest.len());
+            tokens.push((index, end, Some(TokenKind::Comment)));
+            end
+        } else if let Some(comment) = rest.strip_prefix("{-") {
+            let end = comment.find("-}").map_or(code.len(), |end| index + end + 4);
+            tokens.push((index, end, Some(TokenKind::Comment)));
+            end
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let mut end = index + c.len_utf8();
+            for c in code[end..].chars() {
+                if !is_word_char(c) || code[end..].starts_with("--") {
+                    break;
+                }
+                end += c.len_utf8();
+            }
+            tokens.push((index, end, None));
+            end
+        } else if ['\\', 'λ', '𝜆'].contains(&c) {
+            tokens.push((index, index + c.len_utf8(), Some(TokenKind::Keyword)));
+            index + c.len_utf8()
+        } else {
+            index + c.len_utf8()
+        };
+        index = end;
+    }
+    tokens
+}
+
+/// The kinds of the words of an expression, in the order they are written.
+struct Words<'a> {
+    kinds: Vec<TokenKind>,
+    scope: Vec<&'a Variable>,
+}
+
+impl<'a> Words<'a> {
+    fn variable(&mut self, x: &Variable) {
+        self.kinds.push(if self.scope.contains(&x) {
+            TokenKind::BoundVariable
+        } else {
+            TokenKind::FreeVariable
+        });
+    }
+
+    fn bind(&mut self, x: &'a Variable) {
+        self.kinds.push(TokenKind::Binder);
+        self.scope.push(x);
+    }
+
+    fn unbind(&mut self, count: usize) {
+        self.scope.truncate(self.scope.len() - count);
+    }
+
+    fn concrete(&mut self, exp: &'a Exp) {
+        match exp {
+            Exp::Var(x) => self.variable(x),
+            Exp::Apply(f, x) => {
+                self.concrete(f);
+                self.concrete(x);
+            }
+            Exp::Lambda(x, body) | Exp::Rec(x, body) => {
+                self.kinds.push(TokenKind::Keyword);
+                self.bind(x);
+                self.concrete(body);
+                self.unbind(1);
+            }
+            Exp::Case(e, branches) => {
+                self.kinds.push(TokenKind::Keyword);
+                self.concrete(e);
+                self.kinds.push(TokenKind::Keyword);
+                for branch in branches {
+                    self.kinds.push(TokenKind::Constructor);
+                    branch.parameters.iter().for_each(|x| self.bind(x));
+                    self.concrete(&branch.expression);
+                    self.unbind(branch.parameters.len());
+                }
+            }
+            Exp::Const(_, arguments) => {
+                self.kinds.push(TokenKind::Constructor);
+                arguments
+                    .iter()
+                    .for_each(|argument| self.concrete(argument));
+            }
+        }
+    }
+
+    /// `cons item (cons item … nil)`, with `item` adding the words of each
+    /// item.
+    fn list<T>(&mut self, items: &'a [T], mut item: impl FnMut(&mut Self, &'a T)) {
+        for element in items {
+            self.kinds.push(TokenKind::Keyword);
+            item(self, element);
+        }
+        self.kinds.push(TokenKind::Keyword);
+    }
+
+    fn abst(&mut self, exp: &'a Exp) {
+        self.kinds.push(TokenKind::Keyword);
+        match exp {
+            Exp::Var(x) => self.variable(x),
+            Exp::Apply(f, x) => {
+                self.abst(f);
+                self.abst(x);
+            }
+            Exp::Lambda(x, body) | Exp::Rec(x, body) => {
+                self.bind(x);
+                self.abst(body);
+                self.unbind(1);
+            }
+            Exp::Case(e, branches) => {
+                self.abst(e);
+                self.list(branches, |words, branch| {
+                    words.kinds.push(TokenKind::Keyword);
+                    words.kinds.push(TokenKind::Constructor);
+                    words.list(&branch.parameters, |words, x| words.bind(x));
+                    words.abst(&branch.expression);
+                    words.unbind(branch.parameters.len());
+                });
+            }
+            Exp::Const(_, arguments) => {
+                self.kinds.push(TokenKind::Constructor);
+                self.list(arguments, |words, argument| words.abst(argument));
+            }
+        }
+    }
+}
+
+/// Classifies the words and comments of `code`, in either syntax, as the
+/// parser reads them, returning their byte ranges. Code which does not parse
+/// is classified by the words alone.
+pub fn tokenize(code: &str) -> Vec<(usize, usize, TokenKind)> {
+    let tokens = lex(code);
+    let mut words = Words {
+        kinds: vec![],
+        scope: vec![],
+    };
+    let abstract_exp = abst::parse(code);
+    let concrete_exp = concrete::parse(code);
+    let keywords: &[&str] = match (&abstract_exp, &concrete_exp) {
+        (Ok(exp), _) => {
+            words.abst(exp);
+            &ABSTRACT_KEYWORDS
+        }
+        (_, Ok(exp)) => {
+            words.concrete(exp);
+            &CONCRETE_KEYWORDS
+        }
+        _ => &[],
+    };
+    let mut kinds = words.kinds.into_iter();
+    let word_count = tokens
+        .iter()
+        .filter(|(_, _, kind)| *kind != Some(TokenKind::Comment))
+        .count();
+    if kinds.len() != word_count {
+        kinds = vec![].into_iter();
+    }
+    tokens
+        .into_iter()
+        .map(|(start, end, kind)| {
+            let kind = match kind {
+                Some(TokenKind::Comment) => TokenKind::Comment,
+                _ => kinds.next().unwrap_or_else(|| {
+                    let word = &code[start..end];
+                    if kind.is_some()
+                        || keywords.contains(&word)
+                        || (keywords.is_empty()
+                            && (CONCRETE_KEYWORDS.contains(&word)
+                                || ABSTRACT_KEYWORDS.contains(&word)))
+                    {
+                        TokenKind::Keyword
+                    } else if word.starts_with(|c: char| c.is_ascii_uppercase()) {
+                        TokenKind::Constructor
+                    } else {
+                        TokenKind::Variable
+                    }
+                }),
+            };
+            (start, end, kind)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use TokenKind::*;
+
+    fn kinds(code: &str) -> Vec<(&str, TokenKind)> {
+        tokenize(code)
+            .into_iter()
+            .map(|(start, end, kind)| (&code[start..end], kind))
+            .collect()
+    }
+
+    #[test]
+    fn test_tokenize() {
+        assert_eq!(
+            kinds("λx. case x of { Suc(n) → f n } -- f is free"),
+            vec![
+                ("λ", Keyword),
+                ("x", Binder),
+                ("case", Keyword),
+                ("x", BoundVariable),
+                ("of", Keyword),
+                ("Suc", Constructor),
+                ("n", Binder),
+                ("f", FreeVariable),
+                ("n", BoundVariable),
+                ("-- f is free", Comment),
+            ]
+        );
+        assert_eq!(
+            kinds("lambda x (const C (cons (var x) nil))"),
+            vec![
+                ("lambda", Keyword),
+                ("x", Binder),
+                ("const", Keyword),
+                ("C", Constructor),
+                ("cons", Keyword),
+                ("var", Keyword),
+                ("x", BoundVariable),
+                ("nil", Keyword),
+            ]
+        );
+        assert_eq!(
+            kinds("rec of = C("),
+            vec![("rec", Keyword), ("of", Keyword), ("C", Constructor)]
+        );
+    }
+}
@@ -0,0 +1,341 @@
+//! Traversals of expressions. Each trait has a method per kind of expression
+//! whose default walks into the subterms, so an implementation only overrides
+//! the cases it is interested in. The walks keep track of the variables bound
+//! around the current subterm in a [`Scope`].
+
+use super::{Branch, Constructor, Exp, Variable};
+
+/// The variables bound around a subterm, the innermost last.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Scope {
+    binders: Vec<Variable>,
+}
+
+impl Scope {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn binders(&self) -> &[Variable] {
+        &self.binders
+    }
+
+    /// Whether an occurrence of `x` here refers to a binder in the expression
+    /// rather than being free in it.
+    pub fn is_bound(&self, x: &str) -> bool {
+        self.binders.iter().any(|binder| binder == x)
+    }
+
+    pub fn depth(&self) -> usize {
+        self.binders.len()
+    }
+
+    /// Binds `binders` around whatever `f` visits.
+    pub fn with<T>(
+        &mut self,
+        binders: impl IntoIterator<Item = Variable>,
+        f: impl FnOnce(&mut Self) -> T,
+    ) -> T {
+        let depth = self.binders.len();
+        self.binders.extend(binders);
+        let result = f(self);
+        self.binders.truncate(depth);
+        result
+    }
+}
+
+pub trait ExpVisitor {
+    /// Visits `exp` at the root, with nothing bound.
+    fn visit(&mut self, exp: &Exp) {
+        self.visit_exp(exp, &mut Scope::new())
+    }
+
+    fn visit_exp(&mut self, exp: &Exp, scope: &mut Scope) {
+        walk_exp(self, exp, scope)
+    }
+
+    fn visit_var(&mut self, _x: &Variable, _scope: &Scope) {}
+
+    /// Called on the variable bound by a λ, a `rec` or a branch, before
+    /// visiting the subterm it is bound in.
+    fn visit_binder(&mut self, _x: &Variable, _scope: &Scope) {}
+
+    fn visit_apply(&mut self, f: &Exp, x: &Exp, scope: &mut Scope) {
+        self.visit_exp(f, scope);
+        self.visit_exp(x, scope);
+    }
+
+    fn visit_lambda(&mut self, x: &Variable, body: &Exp, scope: &mut Scope) {
+        self.visit_binder(x, scope);
+        scope.with(Some(x.clone()), |scope| self.visit_exp(body, scope))
+    }
+
+    fn visit_rec(&mut self, x: &Variable, body: &Exp, scope: &mut Scope) {
+        self.visit_binder(x, scope);
+        scope.with(Some(x.clone()), |scope| self.visit_exp(body, scope))
+    }
+
+    fn visit_case(&mut self, e: &Exp, branches: &[Branch], scope: &mut Scope) {
+        self.visit_exp(e, scope);
+        for branch in branches {
+            self.visit_branch(branch, scope);
+        }
+    }
+
+    fn visit_branch(&mut self, branch: &Branch, scope: &mut Scope) {
+        for parameter in &branch.parameters {
+            self.visit_binder(parameter, scope);
+        }
+        scope.with(branch.parameters.iter().cloned(), |scope| {
+            self.visit_exp(&branch.expression, scope)
+        })
+    }
+
+    fn visit_const(&mut self, _constructor: &Constructor, arguments: &[Exp], scope: &mut Scope) {
+        for argument in arguments {
+            self.visit_exp(argument, scope);
+        }
+    }
+}
+
+/// Calls the method of `visitor` for the kind of `exp`.
+pub fn walk_exp<V: ExpVisitor + ?Sized>(visitor: &mut V, exp: &Exp, scope: &mut Scope) {
+    match exp {
+        Exp::Var(x) => visitor.visit_var(x, scope),
+        Exp::Apply(f, x) => visitor.visit_apply(f, x, scope),
+        Exp::Lambda(x, body) => visitor.visit_lambda(x, body, scope),
+        Exp::Rec(x, body) => visitor.visit_rec(x, body, scope),
+        Exp::Case(e, branches) => visitor.visit_case(e, branches, scope),
+        Exp::Const(constructor, arguments) => visitor.visit_const(constructor, arguments, scope),
+    }
+}
+
+/// Like [`ExpVisitor`], but changing the expression in place. Binders are put
+/// in the scope as [`ExpVisitorMut::visit_binder`] leaves them.
+pub trait ExpVisitorMut {
+    fn visit(&mut self, exp: &mut Exp) {
+        self.visit_exp(exp, &mut Scope::new())
+    }
+
+    fn visit_exp(&mut self, exp: &mut Exp, scope: &mut Scope) {
+        walk_exp_mut(self, exp, scope)
+    }
+
+    fn visit_var(&mut self, _x: &mut Variable, _scope: &Scope) {}
+
+    fn visit_binder(&mut self, _x: &mut Variable, _scope: &Scope) {}
+
+    fn visit_apply(&mut self, f: &mut Exp, x: &mut Exp, scope: &mut Scope) {
+        self.visit_exp(f, scope);
+        self.visit_exp(x, scope);
+    }
+
+    fn visit_lambda(&mut self, x: &mut Variable, body: &mut Exp, scope: &mut Scope) {
+        self.visit_binder(x, scope);
+        scope.with(Some(x.clone()), |scope| self.visit_exp(body, scope))
+    }
+
+    fn visit_rec(&mut self, x: &mut Variable, body: &mut Exp, scope: &mut Scope) {
+        self.visit_binder(x, scope);
+        scope.with(Some(x.clone()), |scope| self.visit_exp(body, scope))
+    }
+
+    fn visit_case(&mut self, e: &mut Exp, branches: &mut Vec<Branch>, scope: &mut Scope) {
+        self.visit_exp(e, scope);
+        for branch in branches {
+            self.visit_branch(branch, scope);
+        }
+    }
+
+    fn visit_branch(&mut self, branch: &mut Branch, scope: &mut Scope) {
+        for parameter in &mut branch.parameters {
+            self.visit_binder(parameter, scope);
+        }
+        let parameters = branch.parameters.clone();
+        scope.with(parameters, |scope| {
+            self.visit_exp(&mut branch.expression, scope)
+        })
+    }
+
+    fn visit_const(
+        &mut self,
+        _constructor: &mut Constructor,
+        arguments: &mut Vec<Exp>,
+        scope: &mut Scope,
+    ) {
+        for argument in arguments {
+            self.visit_exp(argument, scope);
+        }
+    }
+}
+
+pub fn walk_exp_mut<V: ExpVisitorMut + ?Sized>(visitor: &mut V, exp: &mut Exp, scope: &mut Scope) {
+    match exp {
+        Exp::Var(x) => visitor.visit_var(x, scope),
+        Exp::Apply(f, x) => visitor.visit_apply(f, x, scope),
+        Exp::Lambda(x, body) => visitor.visit_lambda(x, body, scope),
+        Exp::Rec(x, body) => visitor.visit_rec(x, body, scope),
+        Exp::Case(e, branches) => visitor.visit_case(e, branches, scope),
+        Exp::Const(constructor, arguments) => visitor.visit_const(constructor, arguments, scope),
+    }
+}
+
+/// Rebuilds an expression bottom-up, each method returning what replaces the
+/// subterm it is given. The defaults rebuild it unchanged from the folded
+/// subterms.
+pub trait ExpFolder {
+    fn fold(&mut self, exp: Exp) -> Exp {
+        self.fold_exp(exp, &mut Scope::new())
+    }
+
+    fn fold_exp(&mut self, exp: Exp, scope: &mut Scope) -> Exp {
+        walk_fold(self, exp, scope)
+    }
+
+    fn fold_var(&mut self, x: Variable, _scope: &Scope) -> Exp {
+        Exp::Var(x)
+    }
+
+    fn fold_binder(&mut self, x: Variable, _scope: &Scope) -> Variable {
+        x
+    }
+
+    fn fold_apply(&mut self, f: Exp, x: Exp, scope: &mut Scope) -> Exp {
+        let f = self.fold_exp(f, scope);
+        let x = self.fold_exp(x, scope);
+        Exp::Apply(Box::new(f), Box::new(x))
+    }
+
+    fn fold_lambda(&mut self, x: Variable, body: Exp, scope: &mut Scope) -> Exp {
+        let x = self.fold_binder(x, scope);
+        let body = scope.with(Some(x.clone()), |scope| self.fold_exp(body, scope));
+        Exp::Lambda(x, Box::new(body))
+    }
+
+    fn fold_rec(&mut self, x: Variable, body: Exp, scope: &mut Scope) -> Exp {
+        let x = self.fold_binder(x, scope);
+        let body = scope.with(Some(x.clone()), |scope| self.fold_exp(body, scope));
+        Exp::Rec(x, Box::new(body))
+    }
+
+    fn fold_case(&mut self, e: Exp, branches: Vec<Branch>, scope: &mut Scope) -> Exp {
+        let e = self.fold_exp(e, scope);
+        let branches = branches
+            .into_iter()
+            .map(|branch| self.fold_branch(branch, scope))
+            .collect();
+        Exp::Case(Box::new(e), branches)
+    }
+
+    fn fold_branch(&mut self, branch: Branch, scope: &mut Scope) -> Branch {
+        let Branch {
+            constructor,
+            parameters,
+            expression,
+        } = branch;
+        let parameters: Vec<_> = parameters
+            .into_iter()
+            .map(|parameter| self.fold_binder(parameter, scope))
+            .collect();
+        let expression = scope.with(parameters.iter().cloned(), |scope| {
+            self.fold_exp(*expression, scope)
+        });
+        Branch {
+            constructor,
+            parameters,
+            expression: Box::new(expression),
+        }
+    }
+
+    fn fold_const(
+        &mut self,
+        constructor: Constructor,
+        arguments: Vec<Exp>,
+        scope: &mut Scope,
+    ) -> Exp {
+        let arguments = arguments
+            .into_iter()
+            .map(|argument| self.fold_exp(argument, scope))
+            .collect();
+        Exp::Const(constructor, arguments)
+    }
+}
+
+pub fn walk_fold<F: ExpFolder + ?Sized>(folder: &mut F, exp: Exp, scope: &mut Scope) -> Exp {
+    match exp {
+        Exp::Var(x) => folder.fold_var(x, scope),
+        Exp::Apply(f, x) => folder.fold_apply(*f, *x, scope),
+        Exp::Lambda(x, body) => folder.fold_lambda(x, *body, scope),
+        Exp::Rec(x, body) => folder.fold_rec(x, *body, scope),
+        Exp::Case(e, branches) => folder.fold_case(*e, branches, scope),
+        Exp::Const(constructor, arguments) => folder.fold_const(constructor, arguments, scope),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::syntax::concrete;
+
+    /// The free variables, in the order they occur.
+    #[derive(Default)]
+    struct FreeVariables(Vec<Variable>);
+
+    impl ExpVisitor for FreeVariables {
+        fn visit_var(&mut self, x: &Variable, scope: &Scope) {
+            if !scope.is_bound(x) {
+                self.0.push(x.clone());
+            }
+        }
+    }
+
+    struct Prime;
+
+    impl ExpVisitorMut for Prime {
+        fn visit_var(&mut self, x: &mut Variable, _scope: &Scope) {
+            x.push('\'');
+        }
+
+        fn visit_binder(&mut self, x: &mut Variable, _scope: &Scope) {
+            x.push('\'');
+        }
+    }
+
+    /// Replaces the free occurrences of a variable.
+    struct Replace<'a>(&'a str, Exp);
+
+    impl ExpFolder for Replace<'_> {
+        fn fold_var(&mut self, x: Variable, scope: &Scope) -> Exp {
+            if x == self.0 && !scope.is_bound(&x) {
+                self.1.clone()
+            } else {
+                Exp::Var(x)
+            }
+        }
+    }
+
+    #[test]
+    fn test_visitors() {
+        let exp =
+            concrete::parse("λx. case y of { C(y, z) → f x y z; D() → (rec f = f) y }").unwrap();
+
+        let mut free = FreeVariables::default();
+        free.visit(&exp);
+        assert_eq!(free.0, vec!["y", "f", "y"]);
+
+        let mut primed = exp.clone();
+        Prime.visit(&mut primed);
+        assert_eq!(
+            primed,
+            concrete::parse("λx'. case y' of { C(y', z') → f' x' y' z'; D() → (rec f' = f') y' }")
+                .unwrap()
+        );
+
+        let replaced = Replace("y", Exp::Const("Y".to_string(), vec![])).fold(exp);
+        assert_eq!(
+            replaced,
+            concrete::parse("λx. case Y() of { C(y, z) → f x y z; D() → (rec f = f) Y() }")
+                .unwrap()
+        );
+    }
+}
@@ -0,0 +1,796 @@
+//! Compilation of closed χ programs to Turing machines.
+//!
+//! The program is first lowered to S, K and I combinators in which every
+//! constructor stays an atom. The atom of a constructor `C` with `n` arguments
+//! in a signature of `k` constructors behaves like a Scott-encoded value once
+//! it has `n + k` arguments, `C a1 … an h0 … h(k-1) = hj a1 … an` when `C` is
+//! constructor `j`, while with its `n` arguments alone it is data.
+//!
+//! Terms are written on the tape in prefix notation, with `@` for application,
+//! and the machine rewrites the term in place to normal form in normal order.
+//! A value `C(v1, …, vn)` thus ends up as `n` times `@`, the symbol of `C` and
+//! the values `v1` to `vn`, which is also how an input is written.
+
+use std::{collections::BTreeMap, fmt, iter::Peekable, str::Chars};
+
+use crate::{
+    lowering::{
+        scott::{fixpoint, ArityMismatch, Signature},
+        ski,
+    },
+    syntax::{apply_all, lambda_all, Constructor, Exp, Variable},
+    turing::{Direction, Machine, State, Symbol, Tape, Transition, BLANK},
+};
+
+const APPLY: usize = 0;
+const S: usize = 1;
+const K: usize = 2;
+const I: usize = 3;
+/// The atom left by a `case` without a branch for the scrutinee.
+const STUCK: usize = 4;
+const ATOMS: [Symbol; 5] = ['@', 'S', 'K', 'I', '?'];
+const CONSTRUCTOR_SYMBOLS: &str = "abcdefghijklmnopqrstuvwxyzABCDEFGHJLMNOPQRTUVWXYZ0123456789";
+/// Marks the cell being deleted while the rest of the tape is shifted over it.
+const HOLE: Symbol = '#';
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CompileError {
+    ArityMismatch(ArityMismatch),
+    FreeVariable(Variable),
+    /// There are more constructors than symbols to write them with.
+    TooManyConstructors,
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompileError::ArityMismatch(mismatch) => mismatch.fmt(f),
+            CompileError::FreeVariable(x) => write!(f, "free variable {}", x),
+            CompileError::TooManyConstructors => write!(
+                f,
+                "at most {} constructors are supported",
+                CONSTRUCTOR_SYMBOLS.len()
+            ),
+        }
+    }
+}
+
+/// A compiled program together with the symbols of its constructors.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Compiled {
+    pub machine: Machine,
+    constructors: Vec<(Constructor, usize, Symbol)>,
+}
+
+impl Compiled {
+    /// Writes a value as the input of the machine, or returns `None` if it
+    /// is not built from constructors of the signature.
+    pub fn encode(&self, value: &Exp) -> Option<String> {
+        let Exp::Const(c, arguments) = value else {
+            return None;
+        };
+        let &(_, arity, symbol) = self.constructors.iter().find(|(d, _, _)| d == c)?;
+        if arity != arguments.len() {
+            return None;
+        }
+        let mut tape = "@".repeat(arity);
+        tape.push(symbol);
+        for argument in arguments {
+            tape.push_str(&self.encode(argument)?);
+        }
+        Some(tape)
+    }
+
+    /// Reads the value left on the tape by a halted machine.
+    pub fn decode(&self, tape: &Tape) -> Option<Exp> {
+        let contents = tape.contents();
+        let mut symbols = contents.chars().peekable();
+        let value = self.decode_symbols(&mut symbols)?;
+        symbols.next().is_none().then_some(value)
+    }
+
+    fn decode_symbols(&self, symbols: &mut Peekable<Chars>) -> Option<Exp> {
+        let mut applications = 0;
+        while symbols.next_if_eq(&'@').is_some() {
+            applications += 1;
+        }
+        let symbol = symbols.next()?;
+        let (c, arity, _) = self.constructors.iter().find(|(_, _, s)| *s == symbol)?;
+        if *arity != applications {
+            return None;
+        }
+        let arguments = (0..applications)
+            .map(|_| self.decode_symbols(symbols))
+            .collect::<Option<_>>()?;
+        Some(Exp::Const(c.clone(), arguments))
+    }
+}
+
+fn atom(name: &str) -> Exp {
+    Exp::Const(name.to_string(), vec![])
+}
+
+/// The atom of constructor `c`, prefixed so it cannot be mistaken for a
+/// combinator.
+fn constructor_atom(c: &str) -> Exp {
+    atom(&format!("#{}", c))
+}
+
+/// Lowers `exp` to a λ-term over constructor atoms. Branches are not thunked
+/// as in [`crate::lowering::scott`], since the machine reduces in normal order.
+fn lower(exp: &Exp, signature: &Signature) -> Exp {
+    match exp {
+        Exp::Var(x) => Exp::Var(x.clone()),
+        Exp::Apply(f, x) => {
+            Exp::Apply(Box::new(lower(f, signature)), Box::new(lower(x, signature)))
+        }
+        Exp::Lambda(x, exp) => Exp::Lambda(x.clone(), Box::new(lower(exp, signature))),
+        Exp::Rec(x, exp) => Exp::Apply(
+            Box::new(fixpoint()),
+            Box::new(Exp::Lambda(x.clone(), Box::new(lower(exp, signature)))),
+        ),
+        Exp::Const(c, exps) => apply_all(
+            constructor_atom(c),
+            exps.iter().map(|exp| lower(exp, signature)),
+        ),
+        Exp::Case(exp, branches) => {
+            let handlers = signature.constructors().iter().map(|(c, arity)| {
+                match branches.iter().find(|branch| &branch.constructor == c) {
+                    Some(branch) => lambda_all(
+                        branch.parameters.clone().into_iter(),
+                        lower(&branch.expression, signature),
+                    ),
+                    None => lambda_all(vec!["_".to_string(); *arity].into_iter(), atom("?")),
+                }
+            });
+            apply_all(lower(exp, signature), handlers.collect::<Vec<_>>())
+        }
+    }
+}
+
+fn write_term(
+    exp: &Exp,
+    symbols: &BTreeMap<&str, Symbol>,
+    tape: &mut Vec<Symbol>,
+) -> Result<(), CompileError> {
+    match exp {
+        Exp::Apply(f, x) => {
+            tape.push(ATOMS[APPLY]);
+            write_term(f, symbols, tape)?;
+            write_term(x, symbols, tape)
+        }
+        Exp::Var(x) => Err(CompileError::FreeVariable(x.clone())),
+        Exp::Const(c, _) => {
+            let symbol = match c.strip_prefix('#') {
+                Some(c) => symbols[c],
+                None => c.chars().next().unwrap(),
+            };
+            tape.push(symbol);
+            Ok(())
+        }
+        _ => unreachable!("combinator code only contains applications and atoms"),
+    }
+}
+
+/// Marks on a cell, each giving a separate copy of the tape alphabet.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Tag {
+    Plain,
+    /// Part of the normal form already computed.
+    Final,
+    /// The atom whose rule is being applied.
+    Head,
+    /// Where the next symbol is inserted.
+    Mark,
+    /// Matched while looking for the end of a subterm.
+    Crossed,
+    /// The first cell of the subterm whose end is looked for.
+    Open,
+    OpenCrossed,
+    /// A subterm to be copied.
+    Region,
+    /// A subterm to be deleted.
+    Dead,
+}
+
+struct Generator {
+    /// The plain symbol of each atom, the constructors after [`ATOMS`].
+    symbols: Vec<Symbol>,
+    /// The number of arguments after which each atom reduces.
+    arities: Vec<Option<usize>>,
+    transitions: Vec<Transition>,
+    states: usize,
+}
+
+impl Generator {
+    fn cell(&self, base: usize, tag: Tag) -> Symbol {
+        match tag {
+            Tag::Plain => self.symbols[base],
+            _ => char::from_u32(0x4E00 + 0x100 * tag as u32 + base as u32).unwrap(),
+        }
+    }
+
+    fn cells(&self, tags: &[Tag]) -> Vec<Symbol> {
+        tags.iter()
+            .flat_map(|&tag| (0..self.symbols.len()).map(move |base| (base, tag)))
+            .map(|(base, tag)| self.cell(base, tag))
+            .collect()
+    }
+
+    fn state(&mut self, name: &str) -> State {
+        self.states += 1;
+        format!("{}{}", name, self.states)
+    }
+
+    fn add(
+        &mut self,
+        state: &State,
+        read: Option<Symbol>,
+        write: Option<Symbol>,
+        direction: Direction,
+        next: &State,
+    ) {
+        self.transitions.push(Transition {
+            state: state.clone(),
+            read,
+            write,
+            direction,
+            next: next.clone(),
+        });
+    }
+
+    /// Moves in `direction` on any symbol without a transition of its own.
+    fn otherwise(&mut self, state: &State, direction: Direction) {
+        self.add(state, None, None, direction, state);
+    }
+
+    /// Moves one cell in `direction` and continues in a fresh state.
+    fn step(&mut self, state: &State, direction: Direction) -> State {
+        let next = self.state("step");
+        self.add(state, None, None, direction, &next);
+        next
+    }
+
+    /// Moves in `direction` to the first cell tagged `tag`.
+    fn seek(&mut self, state: &State, direction: Direction, tag: Tag, done: &State) {
+        for base in 0..self.symbols.len() {
+            self.add(
+                state,
+                Some(self.cell(base, tag)),
+                None,
+                Direction::Stay,
+                done,
+            );
+        }
+        self.otherwise(state, direction);
+    }
+
+    fn write(
+        &mut self,
+        state: &State,
+        read: Option<Symbol>,
+        text: &[Symbol],
+        direction: Direction,
+        done: &State,
+    ) {
+        let mut state = state.clone();
+        let mut read = read;
+        for (i, &symbol) in text.iter().enumerate() {
+            let (direction, next) = if i + 1 == text.len() {
+                (Direction::Stay, done.clone())
+            } else {
+                (direction, self.state("write"))
+            };
+            self.add(&state, read, Some(symbol), direction, &next);
+            state = next;
+            read = None;
+        }
+    }
+
+    /// Deletes the cell under the head by shifting the cells right of it, all
+    /// tagged with one of `carry`, one to the left. The head stays in place.
+    fn delete(&mut self, state: &State, carry: &[Tag], done: &State) {
+        let end = self.state("delete");
+        self.add(state, None, Some(HOLE), Direction::Right, &end);
+        let cells = self.cells(carry);
+        let carrying: BTreeMap<Symbol, State> = cells
+            .iter()
+            .chain([&BLANK])
+            .map(|&symbol| (symbol, self.state("carry")))
+            .collect();
+        self.add(&end, Some(BLANK), None, Direction::Left, &carrying[&BLANK]);
+        self.otherwise(&end, Direction::Right);
+        for (&symbol, state) in &carrying {
+            self.add(state, Some(HOLE), Some(symbol), Direction::Stay, done);
+            for &cell in &cells {
+                self.add(
+                    state,
+                    Some(cell),
+                    Some(symbol),
+                    Direction::Left,
+                    &carrying[&cell],
+                );
+            }
+        }
+    }
+
+    /// Moves right from the head, deleting every cell tagged `tag`, until the
+    /// end of the tape.
+    fn delete_all(&mut self, state: &State, tag: Tag, carry: &[Tag], done: &State) {
+        let delete = self.state("delete");
+        for base in 0..self.symbols.len() {
+            self.add(
+                state,
+                Some(self.cell(base, tag)),
+                None,
+                Direction::Stay,
+                &delete,
+            );
+        }
+        self.add(state, Some(BLANK), None, Direction::Stay, done);
+        self.otherwise(state, Direction::Right);
+        self.delete(&delete, carry, state);
+    }
+
+    /// States which insert a symbol under the head, shifting the cells right
+    /// of it, all tagged with one of `carry`, one to the right. Entering the
+    /// state of a symbol inserts that symbol.
+    fn insert(&mut self, carry: &[Tag], first: &[Symbol], done: &State) -> BTreeMap<Symbol, State> {
+        let cells = self.cells(carry);
+        let inserting: BTreeMap<Symbol, State> = cells
+            .iter()
+            .chain(first)
+            .map(|&symbol| (symbol, self.state("insert")))
+            .collect();
+        for (&symbol, state) in &inserting {
+            for &cell in &cells {
+                self.add(
+                    state,
+                    Some(cell),
+                    Some(symbol),
+                    Direction::Right,
+                    &inserting[&cell],
+                );
+            }
+            self.add(state, Some(BLANK), Some(symbol), Direction::Stay, done);
+        }
+        inserting
+    }
+
+    /// Moves the head from the first cell of a subterm to its last, tagging
+    /// the subterm with `region`. Every application is matched with an atom
+    /// like an opening with a closing parenthesis, the first cell also opening
+    /// the subterm itself.
+    fn skip(&mut self, state: &State, region: Tag, done: &State) {
+        let right = self.state("right");
+        let left = self.state("left");
+        let clean = self.state("clean");
+        for base in 1..self.symbols.len() {
+            let (plain, tagged) = (self.cell(base, Tag::Plain), self.cell(base, region));
+            self.add(state, Some(plain), Some(tagged), Direction::Stay, done);
+            let crossed = self.cell(base, Tag::Crossed);
+            self.add(&right, Some(plain), Some(crossed), Direction::Left, &left);
+        }
+        let apply = |tag| self.cell(APPLY, tag);
+        let (plain, open, open_crossed) =
+            (apply(Tag::Plain), apply(Tag::Open), apply(Tag::OpenCrossed));
+        let (crossed, tagged) = (apply(Tag::Crossed), apply(region));
+        self.add(state, Some(plain), Some(open), Direction::Right, &right);
+        self.otherwise(&right, Direction::Right);
+        self.add(&left, Some(plain), Some(crossed), Direction::Right, &right);
+        self.add(
+            &left,
+            Some(open),
+            Some(open_crossed),
+            Direction::Right,
+            &right,
+        );
+        self.add(
+            &left,
+            Some(open_crossed),
+            Some(tagged),
+            Direction::Right,
+            &clean,
+        );
+        self.otherwise(&left, Direction::Left);
+        for base in 0..self.symbols.len() {
+            let (crossed, tagged) = (self.cell(base, Tag::Crossed), self.cell(base, region));
+            self.add(
+                &clean,
+                Some(crossed),
+                Some(tagged),
+                Direction::Right,
+                &clean,
+            );
+        }
+        self.add(&clean, None, None, Direction::Left, done);
+    }
+
+    /// Inserts the cells tagged [`Tag::Region`] after the cell tagged
+    /// [`Tag::Mark`], which is left of them, tagging the originals `copied`.
+    fn copy(&mut self, state: &State, copied: Tag, carry: &[Tag], done: &State) {
+        let scan = self.state("scan");
+        let marks: Vec<_> = (0..self.symbols.len())
+            .map(|base| self.cell(base, Tag::Mark))
+            .collect();
+        let inserting = self.insert(carry, &marks, state);
+        for base in 0..self.symbols.len() {
+            self.add(state, Some(marks[base]), None, Direction::Right, &scan);
+            let back = self.state("back");
+            let (region, copied) = (self.cell(base, Tag::Region), self.cell(base, copied));
+            self.add(&scan, Some(region), Some(copied), Direction::Left, &back);
+            for other in 0..self.symbols.len() {
+                let plain = self.cell(other, Tag::Plain);
+                self.add(
+                    &back,
+                    Some(marks[other]),
+                    Some(plain),
+                    Direction::Right,
+                    &inserting[&marks[base]],
+                );
+            }
+            self.otherwise(&back, Direction::Left);
+        }
+        self.otherwise(state, Direction::Left);
+        self.add(&scan, Some(BLANK), None, Direction::Stay, done);
+        self.otherwise(&scan, Direction::Right);
+    }
+
+    /// `@ I x` becomes `x`.
+    fn rule_i(&mut self, state: &State, reduce: &State) {
+        let apply = self.state("i");
+        self.delete(state, &[Tag::Plain], &apply);
+        let apply = self.step(&apply, Direction::Left);
+        self.delete(&apply, &[Tag::Plain], reduce);
+    }
+
+    /// `@ @ K x y` becomes `x`.
+    fn rule_k(&mut self, state: &State, reduce: &State) {
+        let x = self.step(state, Direction::Right);
+        let x_end = self.state("k");
+        self.skip(&x, Tag::Plain, &x_end);
+        let y = self.step(&x_end, Direction::Right);
+        let (y_end, head) = (self.state("k"), self.state("k"));
+        self.skip(&y, Tag::Dead, &y_end);
+        self.seek(&y_end, Direction::Left, Tag::Head, &head);
+        let (end, combinator) = (self.state("k"), self.state("k"));
+        self.delete_all(&head, Tag::Dead, &[Tag::Plain, Tag::Dead], &end);
+        self.seek(&end, Direction::Left, Tag::Head, &combinator);
+        let mut state = combinator;
+        for _ in 0..2 {
+            let apply = self.state("k");
+            self.delete(&state, &[Tag::Plain], &apply);
+            state = self.step(&apply, Direction::Left);
+        }
+        self.delete(&state, &[Tag::Plain], reduce);
+    }
+
+    /// `@ @ @ S x y z` becomes `@ @ x z @ y z`.
+    fn rule_s(&mut self, state: &State, reduce: &State) {
+        let x = self.step(state, Direction::Right);
+        let x_end = self.state("s");
+        self.skip(&x, Tag::Plain, &x_end);
+        let y = self.state("s");
+        for base in 0..self.symbols.len() {
+            let (plain, mark) = (self.cell(base, Tag::Plain), self.cell(base, Tag::Mark));
+            self.add(&x_end, Some(plain), Some(mark), Direction::Right, &y);
+        }
+        let y_end = self.state("s");
+        self.skip(&y, Tag::Plain, &y_end);
+        let z = self.step(&y_end, Direction::Right);
+        let (z_end, copied) = (self.state("s"), self.state("s"));
+        self.skip(&z, Tag::Region, &z_end);
+        self.copy(&z_end, Tag::Plain, &[Tag::Plain, Tag::Region], &copied);
+        let inserted = self.state("s");
+        let inserting = self.insert(&[Tag::Plain], &[], &inserted);
+        for base in 0..self.symbols.len() {
+            let (mark, plain) = (self.cell(base, Tag::Mark), self.cell(base, Tag::Plain));
+            self.add(
+                &copied,
+                Some(mark),
+                Some(plain),
+                Direction::Right,
+                &inserting[&ATOMS[APPLY]],
+            );
+        }
+        self.otherwise(&copied, Direction::Left);
+        let combinator = self.state("s");
+        self.seek(&inserted, Direction::Left, Tag::Head, &combinator);
+        let apply = self.state("s");
+        self.delete(&combinator, &[Tag::Plain], &apply);
+        let apply = self.step(&apply, Direction::Left);
+        self.delete(&apply, &[Tag::Plain], reduce);
+    }
+
+    /// Tags the handlers applied to constructor `index` to be deleted, except
+    /// the one of the constructor, which is copied by [`Self::rule_constructor`].
+    fn rule_handlers(&mut self, state: &State, index: usize, arity: usize, done: &State) {
+        let selectors = self.symbols.len() - ATOMS.len();
+        let mut state = self.step(state, Direction::Right);
+        for i in 0..arity + selectors {
+            let tag = if i == arity + index {
+                Tag::Region
+            } else if i < arity {
+                Tag::Plain
+            } else {
+                Tag::Dead
+            };
+            let end = self.state("c");
+            self.skip(&state, tag, &end);
+            state = end;
+            if i + 1 < arity + selectors {
+                state = self.step(&state, Direction::Right);
+            }
+        }
+        self.add(&state, None, None, Direction::Stay, done);
+    }
+
+    /// `@ .. @ C a1 .. an h0 .. h(k-1)` becomes `@ .. @ hj a1 .. an`, once the
+    /// handlers have been tagged by [`Self::rule_handlers`].
+    fn rule_constructor(&mut self, state: &State, reduce: &State) {
+        let all = [Tag::Plain, Tag::Region, Tag::Dead];
+        let constructor = self.state("c");
+        self.seek(state, Direction::Left, Tag::Head, &constructor);
+        let deleted = self.state("c");
+        self.delete(&constructor, &all, &deleted);
+        let apply = self.step(&deleted, Direction::Left);
+        let (marked, copied) = (self.state("c"), self.state("c"));
+        let (plain, mark) = (self.cell(APPLY, Tag::Plain), self.cell(APPLY, Tag::Mark));
+        self.add(&apply, Some(plain), Some(mark), Direction::Stay, &marked);
+        self.copy(&marked, Tag::Dead, &all, &copied);
+        let start = self.state("c");
+        for base in 0..self.symbols.len() {
+            let (mark, plain) = (self.cell(base, Tag::Mark), self.cell(base, Tag::Plain));
+            self.add(&copied, Some(mark), Some(plain), Direction::Stay, &start);
+        }
+        self.otherwise(&copied, Direction::Left);
+        let first = self.state("c");
+        self.add(&start, Some(BLANK), None, Direction::Right, &first);
+        self.otherwise(&start, Direction::Left);
+        let end = self.state("c");
+        self.delete_all(&first, Tag::Dead, &[Tag::Plain, Tag::Dead], &end);
+        // the spine starts at the first cell not yet in normal form
+        let start = self.step(&end, Direction::Left);
+        let cursor = self.state("c");
+        self.add(&start, Some(BLANK), None, Direction::Right, &cursor);
+        self.otherwise(&start, Direction::Left);
+        let mut state = self.state("c");
+        for base in 0..self.symbols.len() {
+            self.add(
+                &cursor,
+                Some(self.cell(base, Tag::Final)),
+                None,
+                Direction::Right,
+                &cursor,
+            );
+        }
+        self.add(&cursor, None, None, Direction::Stay, &state);
+        for i in 0..self.symbols.len() - ATOMS.len() {
+            let next = if i + 1 == self.symbols.len() - ATOMS.len() {
+                reduce.clone()
+            } else {
+                self.state("c")
+            };
+            self.delete(&state, &[Tag::Plain], &next);
+            state = next;
+        }
+    }
+
+    fn generate(mut self, program: &[Symbol]) -> Machine {
+        let (start, reduce, cursor) = ("0".to_string(), "reduce".to_string(), "cursor".to_string());
+        let (cleanup, finalise, halt) = (
+            "cleanup".to_string(),
+            "final".to_string(),
+            "halt".to_string(),
+        );
+
+        // write the program, applied to the input if there is one
+        self.write(&start, Some(BLANK), program, Direction::Right, &reduce);
+        let applied: Vec<_> = [ATOMS[APPLY]]
+            .iter()
+            .chain(program)
+            .rev()
+            .copied()
+            .collect();
+        let write = self.step(&start, Direction::Left);
+        self.write(&write, None, &applied, Direction::Left, &reduce);
+
+        self.add(&reduce, Some(BLANK), None, Direction::Right, &cursor);
+        self.otherwise(&reduce, Direction::Left);
+        for base in 0..self.symbols.len() {
+            let (plain, done) = (self.cell(base, Tag::Plain), self.cell(base, Tag::Final));
+            self.add(&cursor, Some(done), None, Direction::Right, &cursor);
+            self.add(&cleanup, Some(done), Some(plain), Direction::Left, &cleanup);
+        }
+        self.add(&cursor, Some(BLANK), None, Direction::Left, &cleanup);
+        self.add(&cleanup, Some(BLANK), None, Direction::Right, &halt);
+
+        // count the applications on the spine up to the most any rule needs
+        let needed = self.arities.iter().flatten().copied().max().unwrap();
+        let spine: Vec<_> = (0..=needed).map(|i| format!("spine{}", i)).collect();
+        self.add(&cursor, None, None, Direction::Stay, &spine[0]);
+        let mut rules = vec![];
+        for base in 1..self.symbols.len() {
+            let rule = self.state("rule");
+            for (applications, state) in spine.iter().enumerate() {
+                let symbol = self.cell(base, Tag::Plain);
+                match self.arities[base] {
+                    Some(arity) if applications >= arity => self.add(
+                        state,
+                        Some(symbol),
+                        Some(self.cell(base, Tag::Head)),
+                        Direction::Stay,
+                        &rule,
+                    ),
+                    _ => self.add(
+                        state,
+                        Some(symbol),
+                        Some(self.cell(base, Tag::Final)),
+                        Direction::Left,
+                        &finalise,
+                    ),
+                }
+            }
+            rules.push((base, rule));
+        }
+        for (applications, state) in spine.iter().enumerate() {
+            let next = &spine[(applications + 1).min(needed)];
+            self.add(state, Some(ATOMS[APPLY]), None, Direction::Right, next);
+        }
+        let (plain, done) = (self.cell(APPLY, Tag::Plain), self.cell(APPLY, Tag::Final));
+        self.add(
+            &finalise,
+            Some(plain),
+            Some(done),
+            Direction::Left,
+            &finalise,
+        );
+        self.add(&finalise, None, None, Direction::Right, &cursor);
+
+        let constructor = self.state("constructor");
+        for (base, rule) in rules {
+            match base {
+                S => self.rule_s(&rule, &reduce),
+                K => self.rule_k(&rule, &reduce),
+                I => self.rule_i(&rule, &reduce),
+                STUCK => {}
+                _ => {
+                    let arity = self.arities[base].unwrap() - (self.symbols.len() - ATOMS.len());
+                    self.rule_handlers(&rule, base - ATOMS.len(), arity, &constructor);
+                }
+            }
+        }
+        if self.symbols.len() > ATOMS.len() {
+            self.rule_constructor(&constructor, &reduce);
+        }
+        Machine {
+            start,
+            transitions: self.transitions,
+        }
+    }
+}
+
+/// Compiles `exp` with the constructors it mentions, see [`compile_with`].
+pub fn compile(exp: &Exp) -> Result<Compiled, CompileError> {
+    let signature = Signature::of(exp).map_err(CompileError::ArityMismatch)?;
+    compile_with(exp, &signature)
+}
+
+/// Compiles a closed program to a machine which, started on a blank tape,
+/// leaves the encoding of the value of the program, as given by
+/// [`crate::semantic::eval`], on the tape, and started on an encoded value
+/// leaves the encoding of the value of the program applied to it. This holds
+/// for programs whose value is built from constructors: the machine reduces
+/// to normal form, so on a program whose value is a function it leaves
+/// combinator code, or runs forever if the function is recursive.
+/// `signature` must contain every constructor of the program and of its
+/// inputs.
+pub fn compile_with(exp: &Exp, signature: &Signature) -> Result<Compiled, CompileError> {
+    let constructors = signature.constructors();
+    if constructors.len() > CONSTRUCTOR_SYMBOLS.len() {
+        return Err(CompileError::TooManyConstructors);
+    }
+    let constructors: Vec<_> = constructors
+        .iter()
+        .zip(CONSTRUCTOR_SYMBOLS.chars())
+        .map(|((c, arity), symbol)| (c.clone(), *arity, symbol))
+        .collect();
+    let symbols = constructors
+        .iter()
+        .map(|(c, _, symbol)| (c.as_str(), *symbol))
+        .collect();
+    let mut program = vec![];
+    write_term(
+        &ski::compile(&lower(exp, signature)),
+        &symbols,
+        &mut program,
+    )?;
+
+    let generator = Generator {
+        symbols: ATOMS
+            .iter()
+            .copied()
+            .chain(constructors.iter().map(|(_, _, symbol)| *symbol))
+            .collect(),
+        arities: vec![None, Some(3), Some(2), Some(1), None]
+            .into_iter()
+            .chain(
+                constructors
+                    .iter()
+                    .map(|(_, arity, _)| Some(arity + constructors.len())),
+            )
+            .collect(),
+        transitions: vec![],
+        states: 0,
+    };
+    Ok(Compiled {
+        machine: generator.generate(&program),
+        constructors,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        semantic::eval,
+        syntax::concrete,
+        turing::{run, Outcome},
+    };
+
+    fn assert_compiles(program: &str, input: Option<&str>) {
+        let program = concrete::parse(program).unwrap();
+        let input = input.map(|input| concrete::parse(input).unwrap());
+        let applied = match &input {
+            Some(input) => Exp::Apply(Box::new(program.clone()), Box::new(input.clone())),
+            None => program.clone(),
+        };
+        let compiled = compile_with(&program, &Signature::of(&applied).unwrap()).unwrap();
+        let tape = match &input {
+            Some(input) => compiled.encode(input).unwrap(),
+            None => String::new(),
+        };
+        let Outcome::Halted(configuration, _) = run(&compiled.machine, &tape, 100_000_000) else {
+            panic!("the machine did not halt");
+        };
+        assert_eq!(compiled.decode(&configuration.tape), Some(eval(&applied)));
+    }
+
+    #[test]
+    fn test_compile() {
+        assert_compiles("case C(D(), E()) of { C(x, y) -> y }", None);
+        assert_compiles("λn. Suc(Suc(n))", Some("Suc(Zero())"));
+        assert_compiles(
+            "λp. case p of { Pair(a, b) -> Pair(b, a) }",
+            Some("Pair(True(), Pair(False(), True()))"),
+        );
+        assert_compiles(
+            "λp. case p of { Pair(m, n) -> (rec add = λm.λn. case m of {
+                Zero() -> n;
+                Suc(m) -> Suc(add m n)
+            }) m n }",
+            Some("Pair(Suc(Zero()), Suc(Zero()))"),
+        );
+    }
+
+    #[test]
+    fn test_function_value() {
+        // Reducing to normal form unfolds a recursive function forever.
+        let compiled = compile(&concrete::parse("rec f = λx. f x").unwrap()).unwrap();
+        assert!(matches!(
+            run(&compiled.machine, "", 1_000_000),
+            Outcome::OutOfSteps(_)
+        ));
+    }
+
+    #[test]
+    fn test_format() {
+        let compiled = compile(&concrete::parse("λx. x").unwrap()).unwrap();
+        let text = compiled.machine.to_string();
+        assert_eq!(Machine::parse(&text), Ok(compiled.machine));
+        assert_eq!(
+            compile(&concrete::parse("λx. y").unwrap()),
+            Err(CompileError::FreeVariable("y".to_string()))
+        );
+    }
+}
@@ -0,0 +1,240 @@
+use std::sync::LazyLock;
+
+use bimap::BiMap;
+use chi_macros::include_chi;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    convert::{FromChi, ToChi},
+    semantic,
+    syntax::Exp,
+    turing::{is_halting, Configuration, Direction, Machine, State, Symbol, Tape, BLANK},
+};
+
+/// The numbering of states and symbols used to encode machines as χ terms,
+/// playing the role [`crate::bootstrapping::Context`] plays for programs.
+/// The blank is always symbol `0`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Encoding {
+    state: BiMap<State, usize>,
+    symbol: BiMap<Symbol, usize>,
+}
+
+impl Default for Encoding {
+    fn default() -> Self {
+        let mut symbol = BiMap::new();
+        symbol.insert(BLANK, 0);
+        Self {
+            state: BiMap::new(),
+            symbol,
+        }
+    }
+}
+
+impl Encoding {
+    pub fn get_or_create_state_id(&mut self, state: &State) -> usize {
+        if let Some(&id) = self.state.get_by_left(state) {
+            id
+        } else {
+            let id = self.state.len();
+            self.state.insert(state.clone(), id);
+            id
+        }
+    }
+
+    pub fn get_or_create_symbol_id(&mut self, symbol: Symbol) -> usize {
+        if let Some(&id) = self.symbol.get_by_left(&symbol) {
+            id
+        } else {
+            let id = self.symbol.len();
+            self.symbol.insert(symbol, id);
+            id
+        }
+    }
+
+    fn symbols(&self) -> impl Iterator<Item = Symbol> + '_ {
+        self.symbol.left_values().copied()
+    }
+}
+
+/// Encodes the transitions of `machine` as a list of
+/// `Rule(state, read, next, write, direction)` terms. Wildcards are expanded
+/// over the symbols of the machine and those already in `encoding`, so the
+/// tape should be encoded first. Halting states get no rules.
+pub fn decompile_machine(machine: &Machine, encoding: &mut Encoding) -> Exp {
+    for symbol in machine.alphabet() {
+        encoding.get_or_create_symbol_id(symbol);
+    }
+    let mut states: Vec<&State> = vec![];
+    for transition in &machine.transitions {
+        if !is_halting(&transition.state) && !states.contains(&&transition.state) {
+            states.push(&transition.state);
+        }
+    }
+    let symbols: Vec<_> = encoding.symbols().collect();
+    let mut rules = vec![];
+    for state in states {
+        for &symbol in &symbols {
+            let Some(transition) = machine.transition(state, symbol) else {
+                continue;
+            };
+            let direction = match transition.direction {
+                Direction::Left => "Left",
+                Direction::Right => "Right",
+                Direction::Stay => "Stay",
+            };
+            let write = transition.write.unwrap_or(symbol);
+            rules.push(Exp::Const(
+                "Rule".to_string(),
+                vec![
+                    encoding.get_or_create_state_id(state).to_chi(),
+                    encoding.get_or_create_symbol_id(symbol).to_chi(),
+                    encoding.get_or_create_state_id(&transition.next).to_chi(),
+                    encoding.get_or_create_symbol_id(write).to_chi(),
+                    Exp::Const(direction.to_string(), vec![]),
+                ],
+            ));
+        }
+    }
+    rules.to_chi()
+}
+
+/// Encodes a configuration as `Config(state, Tape(left, head, right))`, where
+/// `left` and `right` list the cells nearest to the head first.
+pub fn decompile_configuration(configuration: &Configuration, encoding: &mut Encoding) -> Exp {
+    let tape = &configuration.tape;
+    let mut symbols = |symbols: Vec<Symbol>| -> Vec<Exp> {
+        symbols
+            .into_iter()
+            .map(|symbol| encoding.get_or_create_symbol_id(symbol).to_chi())
+            .collect()
+    };
+    let left = symbols(tape.left()).to_chi();
+    let head = symbols(vec![tape.read()]).remove(0);
+    let right = symbols(tape.right()).to_chi();
+    let state = encoding
+        .get_or_create_state_id(&configuration.state)
+        .to_chi();
+    Exp::Const(
+        "Config".to_string(),
+        vec![
+            state,
+            Exp::Const("Tape".to_string(), vec![left, head, right]),
+        ],
+    )
+}
+
+/// The inverse of [`decompile_configuration`].
+pub fn recompile_configuration(exp: &Exp, encoding: &Encoding) -> Option<Configuration> {
+    let symbol = |id: usize| encoding.symbol.get_by_right(&id).copied();
+    let cells = |exp: &Exp| {
+        Vec::<usize>::from_chi(exp)
+            .ok()?
+            .into_iter()
+            .map(symbol)
+            .collect::<Option<Vec<_>>>()
+    };
+    let Exp::Const(c, arguments) = exp else {
+        return None;
+    };
+    let [state, tape] = &arguments[..] else {
+        return None;
+    };
+    let Exp::Const(t, parts) = tape else {
+        return None;
+    };
+    let [left, head, right] = &parts[..] else {
+        return None;
+    };
+    if c != "Config" || t != "Tape" {
+        return None;
+    }
+    let state = encoding
+        .state
+        .get_by_right(&usize::from_chi(state).ok()?)?
+        .clone();
+    let tape = Tape::from_parts(
+        &cells(left)?,
+        symbol(usize::from_chi(head).ok()?)?,
+        &cells(right)?,
+    );
+    Some(Configuration { state, tape })
+}
+
+static SIMULATE: LazyLock<Exp> = LazyLock::new(|| include_chi!("simulate.chi"));
+
+/// Runs `machine` on `input` with a χ program, which applies the encoded
+/// rules until none matches, and decodes the final configuration. The χ
+/// program runs on [`semantic::Evaluator`], so long runs do not exhaust the
+/// stack, and gives up after `step_limit` steps.
+pub fn self_simulate(machine: &Machine, input: &str, step_limit: usize) -> Option<Configuration> {
+    let mut encoding = Encoding::default();
+    let configuration = decompile_configuration(&Configuration::new(machine, input), &mut encoding);
+    let rules = decompile_machine(machine, &mut encoding);
+    let apply = Exp::Apply(
+        Box::new(Exp::Apply(Box::new(SIMULATE.clone()), Box::new(rules))),
+        Box::new(configuration),
+    );
+    let mut evaluator = semantic::Evaluator::start(&apply);
+    match evaluator.run(step_limit) {
+        semantic::Status::Done => recompile_configuration(evaluator.result()?, &encoding),
+        semantic::Status::Running => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{syntax::concrete, turing::run};
+
+    fn assert_agree(machine: &str, input: &str) {
+        let machine = Machine::parse(machine).unwrap();
+        let native = run(&machine, input, 10_000);
+        let simulated = self_simulate(&machine, input, 1_000_000).unwrap();
+        let native = native.configuration();
+        assert_eq!(simulated.state, native.state);
+        assert_eq!(simulated.tape.contents(), native.tape.contents());
+        assert_eq!(simulated.tape.left(), native.tape.left());
+        assert_eq!(simulated.tape.right(), native.tape.right());
+    }
+
+    #[test]
+    fn test_decompile_configuration() {
+        let machine = Machine::parse("0 a b r halt").unwrap();
+        let configuration = Configuration::new(&machine, "ab");
+        let mut encoding = Encoding::default();
+        let exp = decompile_configuration(&configuration, &mut encoding);
+        assert_eq!(
+            concrete::format(&exp),
+            "Config(Zero(), Tape(Nil(), Suc(Zero()), Cons(Suc(Suc(Zero())), Nil())))"
+        );
+        assert_eq!(
+            recompile_configuration(&exp, &encoding),
+            Some(configuration)
+        );
+    }
+
+    #[test]
+    fn test_self_simulate() {
+        let increment = "
+            0 0 0 r 0
+            0 1 1 r 0
+            0 _ _ l 1
+            1 1 0 l 1
+            1 0 1 * halt
+            1 _ 1 * halt";
+        assert_agree(increment, "1011");
+        assert_agree(increment, "111");
+        assert_agree(increment, "");
+        // wildcards, moving off the left end and getting stuck
+        let machine = "
+            0 a * l 0
+            0 _ x r 1
+            1 * y r 2";
+        assert_agree(machine, "ab");
+        assert_agree(machine, "ba");
+
+        let forever = Machine::parse("0 * * r 0").unwrap();
+        assert_eq!(self_simulate(&forever, "a", 10_000), None);
+    }
+}
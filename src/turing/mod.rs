@@ -0,0 +1,200 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+mod compile;
+mod encode;
+mod simulator;
+
+pub use compile::{compile, compile_with, CompileError, Compiled};
+pub use encode::{
+    decompile_configuration, decompile_machine, recompile_configuration, self_simulate, Encoding,
+};
+pub use simulator::{run, Configuration, Outcome, Tape};
+
+pub type State = String;
+pub type Symbol = char;
+
+/// The blank symbol, which fills every cell of the tape not written yet.
+pub const BLANK: Symbol = '_';
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Direction {
+    Left,
+    Right,
+    Stay,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Transition {
+    pub state: State,
+    /// `None` matches any symbol.
+    pub read: Option<Symbol>,
+    /// `None` writes back the symbol which was read.
+    pub write: Option<Symbol>,
+    pub direction: Direction,
+    pub next: State,
+}
+
+/// A machine in the usual textual format, one transition per line:
+///
+/// ```text
+/// ; <state> <read> <write> <direction> <next state>
+/// 0 1 0 r 0
+/// 0 _ _ * halt
+/// ```
+///
+/// `_` is the blank, `*` is a wildcard for the read and written symbol and
+/// means "stay" as a direction. Execution starts in state `0` and stops in any
+/// state whose name starts with `halt`, or when no transition applies.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Machine {
+    pub start: State,
+    pub transitions: Vec<Transition>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    /// One based line number of the malformed transition.
+    pub line: usize,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "malformed transition on line {}", self.line)
+    }
+}
+
+pub fn is_halting(state: &str) -> bool {
+    state.starts_with("halt")
+}
+
+fn parse_symbol(symbol: &str) -> Result<Option<Symbol>, ()> {
+    let mut chars = symbol.chars();
+    match (chars.next(), chars.next()) {
+        (Some('*'), None) => Ok(None),
+        (Some(c), None) => Ok(Some(c)),
+        _ => Err(()),
+    }
+}
+
+fn parse_transition(line: &str) -> Result<Transition, ()> {
+    let fields: Vec<_> = line.split_whitespace().collect();
+    let [state, read, write, direction, next] = fields[..] else {
+        return Err(());
+    };
+    let direction = match direction {
+        "l" | "L" => Direction::Left,
+        "r" | "R" => Direction::Right,
+        "*" => Direction::Stay,
+        _ => return Err(()),
+    };
+    Ok(Transition {
+        state: state.to_string(),
+        read: parse_symbol(read)?,
+        write: parse_symbol(write)?,
+        direction,
+        next: next.to_string(),
+    })
+}
+
+impl Machine {
+    pub fn parse(code: &str) -> Result<Self, ParseError> {
+        let mut transitions = vec![];
+        for (index, line) in code.lines().enumerate() {
+            let line = line.split(';').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let transition = parse_transition(line).map_err(|_| ParseError { line: index + 1 })?;
+            transitions.push(transition);
+        }
+        Ok(Self {
+            start: "0".to_string(),
+            transitions,
+        })
+    }
+
+    /// The transition taken in `state` when reading `symbol`: an exact match
+    /// wins over a wildcard, and earlier lines win over later ones.
+    pub fn transition(&self, state: &str, symbol: Symbol) -> Option<&Transition> {
+        let mut candidates = self.transitions.iter().filter(|t| t.state == state);
+        candidates
+            .clone()
+            .find(|t| t.read == Some(symbol))
+            .or_else(|| candidates.find(|t| t.read.is_none()))
+    }
+
+    /// Every symbol mentioned by the machine, the blank first.
+    pub fn alphabet(&self) -> Vec<Symbol> {
+        let mut alphabet = vec![BLANK];
+        let mentioned = self
+            .transitions
+            .iter()
+            .flat_map(|t| [t.read, t.write])
+            .flatten();
+        for symbol in mentioned {
+            if !alphabet.contains(&symbol) {
+                alphabet.push(symbol);
+            }
+        }
+        alphabet
+    }
+}
+
+impl fmt::Display for Machine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let symbol = |s: Option<Symbol>| s.unwrap_or('*');
+        for t in &self.transitions {
+            let direction = match t.direction {
+                Direction::Left => 'l',
+                Direction::Right => 'r',
+                Direction::Stay => '*',
+            };
+            writeln!(
+                f,
+                "{} {} {} {} {}",
+                t.state,
+                symbol(t.read),
+                symbol(t.write),
+                direction,
+                t.next
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let machine = Machine::parse(
+            "; flip bits
+            0 0 1 r 0
+            0 1 0 r 0 ; comment
+            0 * * * halt",
+        )
+        .unwrap();
+        assert_eq!(machine.transitions.len(), 3);
+        assert_eq!(
+            machine.transitions[2],
+            Transition {
+                state: "0".to_string(),
+                read: None,
+                write: None,
+                direction: Direction::Stay,
+                next: "halt".to_string(),
+            }
+        );
+        assert_eq!(machine.transition("0", '1').unwrap().write, Some('0'));
+        assert_eq!(machine.transition("0", BLANK).unwrap().next, "halt");
+        assert_eq!(machine.alphabet(), vec![BLANK, '0', '1']);
+        assert_eq!(Machine::parse(&machine.to_string()), Ok(machine));
+
+        assert_eq!(Machine::parse("0 0 1 r"), Err(ParseError { line: 1 }));
+        assert_eq!(Machine::parse("\n0 0 1 x 0"), Err(ParseError { line: 2 }));
+    }
+}
@@ -0,0 +1,227 @@
+use std::collections::{HashMap, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+use crate::turing::{is_halting, Direction, Machine, State, Symbol, Transition, BLANK};
+
+/// A tape which is blank except for finitely many cells.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Tape {
+    cells: VecDeque<Symbol>,
+    head: usize,
+}
+
+impl Tape {
+    /// A tape holding `input` with the head on its first symbol.
+    pub fn new(input: &str) -> Self {
+        let mut cells: VecDeque<_> = input.chars().collect();
+        if cells.is_empty() {
+            cells.push_back(BLANK);
+        }
+        Self { cells, head: 0 }
+    }
+
+    /// Builds a tape from the cells left of the head (nearest first), the
+    /// symbol under the head and the cells right of it (nearest first).
+    pub fn from_parts(left: &[Symbol], head: Symbol, right: &[Symbol]) -> Self {
+        let mut cells: VecDeque<_> = left.iter().rev().copied().collect();
+        let position = cells.len();
+        cells.push_back(head);
+        cells.extend(right);
+        Self {
+            cells,
+            head: position,
+        }
+    }
+
+    pub fn read(&self) -> Symbol {
+        self.cells[self.head]
+    }
+
+    pub fn write(&mut self, symbol: Symbol) {
+        self.cells[self.head] = symbol;
+    }
+
+    pub fn shift(&mut self, direction: Direction) {
+        match direction {
+            Direction::Left if self.head == 0 => self.cells.push_front(BLANK),
+            Direction::Left => self.head -= 1,
+            Direction::Right => {
+                self.head += 1;
+                if self.head == self.cells.len() {
+                    self.cells.push_back(BLANK);
+                }
+            }
+            Direction::Stay => {}
+        }
+    }
+
+    /// The cells left of the head, nearest first, without trailing blanks.
+    pub fn left(&self) -> Vec<Symbol> {
+        let mut left: Vec<_> = self.cells.range(..self.head).rev().copied().collect();
+        while left.last() == Some(&BLANK) {
+            left.pop();
+        }
+        left
+    }
+
+    /// The cells right of the head, nearest first, without trailing blanks.
+    pub fn right(&self) -> Vec<Symbol> {
+        let mut right: Vec<_> = self.cells.range(self.head + 1..).copied().collect();
+        while right.last() == Some(&BLANK) {
+            right.pop();
+        }
+        right
+    }
+
+    /// The written part of the tape, without the surrounding blanks.
+    pub fn contents(&self) -> String {
+        let contents: String = self.cells.iter().collect();
+        contents.trim_matches(BLANK).to_string()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Configuration {
+    pub state: State,
+    pub tape: Tape,
+}
+
+impl Configuration {
+    pub fn new(machine: &Machine, input: &str) -> Self {
+        Self {
+            state: machine.start.clone(),
+            tape: Tape::new(input),
+        }
+    }
+
+    /// Performs one transition, or returns `false` if the machine has stopped.
+    pub fn step(&mut self, machine: &Machine) -> bool {
+        if is_halting(&self.state) {
+            return false;
+        }
+        let Some(transition) = machine.transition(&self.state, self.tape.read()) else {
+            return false;
+        };
+        self.apply(transition);
+        true
+    }
+
+    fn apply(&mut self, transition: &Transition) {
+        if let Some(symbol) = transition.write {
+            self.tape.write(symbol);
+        }
+        self.tape.shift(transition.direction);
+        self.state = transition.next.clone();
+    }
+}
+
+/// The transitions of a machine by state, so that large machines such as the
+/// ones built by [`crate::turing::compile`] can be run without scanning every
+/// line at each step. Agrees with [`Machine::transition`].
+struct Index<'a> {
+    exact: HashMap<(&'a str, Symbol), &'a Transition>,
+    wildcard: HashMap<&'a str, &'a Transition>,
+}
+
+impl<'a> Index<'a> {
+    fn new(machine: &'a Machine) -> Self {
+        let mut exact = HashMap::new();
+        let mut wildcard = HashMap::new();
+        for transition in &machine.transitions {
+            let state = transition.state.as_str();
+            match transition.read {
+                Some(symbol) => exact.entry((state, symbol)).or_insert(transition),
+                None => wildcard.entry(state).or_insert(transition),
+            };
+        }
+        Self { exact, wildcard }
+    }
+
+    fn transition(&self, state: &str, symbol: Symbol) -> Option<&'a Transition> {
+        self.exact
+            .get(&(state, symbol))
+            .or_else(|| self.wildcard.get(state))
+            .copied()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Outcome {
+    /// Reached a halting state after this many steps.
+    Halted(Configuration, usize),
+    /// Reached a state without an applicable transition after this many steps.
+    Stuck(Configuration, usize),
+    OutOfSteps(Configuration),
+}
+
+impl Outcome {
+    pub fn configuration(&self) -> &Configuration {
+        match self {
+            Outcome::Halted(configuration, _)
+            | Outcome::Stuck(configuration, _)
+            | Outcome::OutOfSteps(configuration) => configuration,
+        }
+    }
+}
+
+/// Runs `machine` on `input` for at most `step_limit` steps.
+pub fn run(machine: &Machine, input: &str, step_limit: usize) -> Outcome {
+    let index = Index::new(machine);
+    let mut configuration = Configuration::new(machine, input);
+    for steps in 0..=step_limit {
+        if is_halting(&configuration.state) {
+            return Outcome::Halted(configuration, steps);
+        }
+        if steps == step_limit {
+            break;
+        }
+        match index.transition(&configuration.state, configuration.tape.read()) {
+            Some(transition) => configuration.apply(transition),
+            None => return Outcome::Stuck(configuration, steps),
+        }
+    }
+    Outcome::OutOfSteps(configuration)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INCREMENT: &str = "
+        0 0 0 r 0
+        0 1 1 r 0
+        0 _ _ l 1
+        1 1 0 l 1
+        1 0 1 * halt
+        1 _ 1 * halt";
+
+    #[test]
+    fn test_run() {
+        let machine = Machine::parse(INCREMENT).unwrap();
+        let outcome = run(&machine, "1011", 100);
+        assert!(matches!(outcome, Outcome::Halted(_, 8)));
+        assert_eq!(outcome.configuration().tape.contents(), "1100");
+
+        let outcome = run(&machine, "111", 100);
+        assert_eq!(outcome.configuration().tape.contents(), "1000");
+
+        assert!(matches!(run(&machine, "1011", 3), Outcome::OutOfSteps(_)));
+
+        let machine = Machine::parse("0 1 1 r 0").unwrap();
+        assert!(matches!(run(&machine, "11", 100), Outcome::Stuck(_, 2)));
+    }
+
+    #[test]
+    fn test_tape() {
+        let mut tape = Tape::new("ab");
+        tape.shift(Direction::Left);
+        tape.write('c');
+        assert_eq!(tape.contents(), "cab");
+        assert_eq!(tape.right(), vec!['a', 'b']);
+        assert_eq!(
+            Tape::from_parts(&tape.left(), tape.read(), &tape.right()),
+            tape
+        );
+    }
+}
@@ -0,0 +1,237 @@
+use serde::{de::DeserializeOwned, Serialize};
+use wasm_bindgen::{prelude::wasm_bindgen, JsValue};
+
+use crate::{
+    bootstrapping::{self, Context},
+    error::{utf16_offset, Error, ErrorKind},
+    semantic,
+    syntax::{self, abst, concrete, Exp, Path, Step},
+};
+
+fn from_js<T: DeserializeOwned>(value: JsValue) -> Result<T, Error> {
+    Ok(serde_wasm_bindgen::from_value(value)?)
+}
+
+fn to_js<T: Serialize>(value: &T) -> Result<JsValue, Error> {
+    Ok(serde_wasm_bindgen::to_value(value)?)
+}
+
+/// @throws {ChiError}
+#[wasm_bindgen]
+pub fn parse(code: &str) -> Result<JsValue, Error> {
+    let (expr, _) = syntax::parse(code).map_err(|_| Error::parse(code))?;
+    to_js(&expr)
+}
+
+/// Like [`syntax::tokenize`], but with offsets counted in UTF-16 code units,
+/// as JavaScript strings are indexed.
+#[wasm_bindgen]
+pub fn tokenize(code: &str) -> Result<JsValue, Error> {
+    let tokens: Vec<_> = syntax::tokenize(code)
+        .into_iter()
+        .map(|(start, end, kind)| (utf16_offset(code, start), utf16_offset(code, end), kind))
+        .collect();
+    to_js(&tokens)
+}
+
+/// @throws {ChiError}
+#[wasm_bindgen]
+pub fn format_abstract(exp: JsValue) -> Result<String, Error> {
+    Ok(abst::format(&from_js(exp)?))
+}
+
+/// @throws {ChiError}
+#[wasm_bindgen]
+pub fn format_concrete(exp: JsValue) -> Result<String, Error> {
+    Ok(concrete::format(&from_js(exp)?))
+}
+
+/// @throws {ChiError}
+#[wasm_bindgen]
+pub fn substitute(exp: JsValue, from_variable: &str, to_exp: JsValue) -> Result<JsValue, Error> {
+    let exp: Exp = from_js(exp)?;
+    let to_exp: Exp = from_js(to_exp)?;
+    to_js(&semantic::substitute(&exp, from_variable, &to_exp))
+}
+
+/// Every subterm of `exp` with its [`syntax::Path`], parents first.
+///
+/// @throws {ChiError}
+#[wasm_bindgen]
+pub fn subterms(exp: JsValue) -> Result<JsValue, Error> {
+    let exp: Exp = from_js(exp)?;
+    to_js(&exp.subterms())
+}
+
+fn no_subterm(path: &[Step]) -> Error {
+    Error {
+        kind: ErrorKind::InvalidArgument,
+        message: format!("no subterm at {:?}", path),
+        span: None,
+    }
+}
+
+/// The subterm of `exp` at `path`, a [`syntax::Path`].
+///
+/// @throws {ChiError}
+#[wasm_bindgen]
+pub fn get_subterm(exp: JsValue, path: JsValue) -> Result<JsValue, Error> {
+    let exp: Exp = from_js(exp)?;
+    let path: Path = from_js(path)?;
+    to_js(exp.get(&path).ok_or_else(|| no_subterm(&path))?)
+}
+
+/// `exp` with the subterm at `path` replaced by `new`.
+///
+/// @throws {ChiError}
+#[wasm_bindgen]
+pub fn replace_subterm(exp: JsValue, path: JsValue, new: JsValue) -> Result<JsValue, Error> {
+    let mut exp: Exp = from_js(exp)?;
+    let path: Path = from_js(path)?;
+    exp.replace(&path, from_js(new)?)
+        .map_err(|_| no_subterm(&path))?;
+    to_js(&exp)
+}
+
+/// Every redex of `exp` with its path and [`semantic::RedexKind`], outermost
+/// first.
+///
+/// @throws {ChiError}
+#[wasm_bindgen]
+pub fn redexes(exp: JsValue) -> Result<JsValue, Error> {
+    let exp: Exp = from_js(exp)?;
+    to_js(&semantic::redexes(&exp))
+}
+
+/// `exp` with the redex at `path` contracted.
+///
+/// @throws {ChiError}
+#[wasm_bindgen]
+pub fn contract(exp: JsValue, path: JsValue) -> Result<JsValue, Error> {
+    let exp: Exp = from_js(exp)?;
+    let path: Path = from_js(path)?;
+    let result = semantic::contract(&exp, &path).ok_or_else(|| Error {
+        kind: ErrorKind::InvalidArgument,
+        message: format!("no redex at {:?}", path),
+        span: None,
+    })?;
+    to_js(&result)
+}
+
+/// The smallest subterms in which `left` and `right` differ, see
+/// [`syntax::diff::Diff`], ignoring the names of binders if `modulo_alpha`.
+///
+/// @throws {ChiError}
+#[wasm_bindgen]
+pub fn diff(left: JsValue, right: JsValue, modulo_alpha: bool) -> Result<JsValue, Error> {
+    let left: Exp = from_js(left)?;
+    let right: Exp = from_js(right)?;
+    let differences = if modulo_alpha {
+        syntax::diff::diff_modulo_alpha(&left, &right)
+    } else {
+        syntax::diff::diff(&left, &right)
+    };
+    to_js(&differences)
+}
+
+/// `exp` with the reductions evaluating it is sure to do first done, see
+/// [`semantic::simplify`].
+///
+/// @throws {ChiError}
+#[wasm_bindgen]
+pub fn simplify(exp: JsValue) -> Result<JsValue, Error> {
+    let exp: Exp = from_js(exp)?;
+    to_js(&semantic::simplify(&exp))
+}
+
+/// The number of subterms of `exp`.
+///
+/// @throws {ChiError}
+#[wasm_bindgen]
+pub fn size(exp: JsValue) -> Result<usize, Error> {
+    let exp: Exp = from_js(exp)?;
+    Ok(exp.size())
+}
+
+/// Evaluates `exp`, failing if a value is matched against a branch with
+/// another number of parameters.
+///
+/// @throws {ChiError}
+#[wasm_bindgen]
+pub fn eval_chi(exp: JsValue) -> Result<JsValue, Error> {
+    let exp: Exp = from_js(exp)?;
+    let mut evaluator = semantic::Evaluator::start(&exp);
+    evaluator.try_run(usize::MAX)?;
+    to_js(&evaluator.result())
+}
+
+/// The binders of `exp` and the occurrences they bind, see
+/// [`semantic::ScopeInfo`].
+///
+/// @throws {ChiError}
+#[wasm_bindgen]
+pub fn resolve(exp: JsValue) -> Result<JsValue, Error> {
+    let exp: Exp = from_js(exp)?;
+    to_js(&semantic::resolve(&exp))
+}
+
+/// Renames the binder at `binder`, a [`semantic::BinderPath`], unless that
+/// would change the meaning of `exp`.
+///
+/// @throws {ChiError}
+#[wasm_bindgen]
+pub fn rename_binder(exp: JsValue, binder: JsValue, new_name: &str) -> Result<JsValue, Error> {
+    let exp: Exp = from_js(exp)?;
+    let binder: semantic::BinderPath = from_js(binder)?;
+    to_js(&semantic::rename_binder(&exp, &binder, new_name)?)
+}
+
+/// @throws {ChiError}
+#[wasm_bindgen]
+pub fn check_termination(exp: JsValue) -> Result<Vec<String>, Error> {
+    let exp: Exp = from_js(exp)?;
+    Ok(semantic::check_termination(&exp)
+        .iter()
+        .map(ToString::to_string)
+        .collect())
+}
+
+/// @throws {ChiError}
+#[wasm_bindgen]
+pub fn standard_form(exp: JsValue, context: Option<Context>) -> Result<JsValue, Error> {
+    let exp: Exp = from_js(exp)?;
+    let mut context = context.unwrap_or_default();
+    let result = bootstrapping::decompile(&exp, &mut context);
+    to_js(&(result, context))
+}
+
+/// @throws {ChiError}
+#[wasm_bindgen]
+pub fn get_context_object(v: JsValue) -> Result<Context, Error> {
+    from_js(v)
+}
+
+/// @throws {ChiError}
+#[wasm_bindgen]
+pub fn self_interpret(exp: JsValue, context: Option<Context>) -> Result<JsValue, Error> {
+    let exp: Exp = from_js(exp)?;
+    let mut context = context.unwrap_or_default();
+    let result = bootstrapping::self_interpret(&exp, &mut context);
+    to_js(&(result, context))
+}
+
+/// @throws {ChiError}
+#[wasm_bindgen]
+pub fn self_substitute(
+    from: &str,
+    to: JsValue,
+    exp: JsValue,
+    context: Option<Context>,
+) -> Result<JsValue, Error> {
+    let from = from.to_string();
+    let exp: Exp = from_js(exp)?;
+    let to: Exp = from_js(to)?;
+    let mut context = context.unwrap_or_default();
+    let result = bootstrapping::self_substitute(&from, &to, &exp, &mut context);
+    to_js(&(result, context))
+}
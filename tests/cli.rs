@@ -0,0 +1,119 @@
+use std::{
+    env, fs,
+    path::PathBuf,
+    process::{Command, Output},
+};
+
+fn chi(arguments: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_chi"))
+        .args(arguments)
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> &str {
+    std::str::from_utf8(&output.stdout).unwrap().trim_end()
+}
+
+/// A file in a directory of its own, so tests may run in parallel.
+fn file(test: &str, name: &str, contents: &str) -> PathBuf {
+    let directory = env::temp_dir().join(format!("chi-cli-{}-{}", test, std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    let path = directory.join(name);
+    fs::write(&path, contents).unwrap();
+    path
+}
+
+#[test]
+fn test_run() {
+    let program = file("run", "add.chi", "case C(D(), E()) of { C(x, y) → y }");
+    let output = chi(&["run", program.to_str().unwrap()]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "E()");
+
+    let output = chi(&["run", program.to_str().unwrap(), "--output", "abstract"]);
+    assert_eq!(stdout(&output), "const E (nil)");
+
+    let program = file("run", "loop.chi", "rec x = x");
+    let output = chi(&["run", program.to_str().unwrap(), "--limit", "100"]);
+    assert_eq!(output.status.code(), Some(1));
+    // the default limit is reached long before the stack runs out
+    let output = chi(&["run", program.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("did not finish within"));
+
+    let program = file("run", "invalid.chi", "C(");
+    assert_eq!(
+        chi(&["run", program.to_str().unwrap()]).status.code(),
+        Some(65)
+    );
+    assert_eq!(chi(&["run", "/nonexistent.chi"]).status.code(), Some(74));
+    assert_eq!(chi(&["walk"]).status.code(), Some(2));
+}
+
+#[test]
+fn test_fmt() {
+    let program = file("fmt", "id.chi", "(λx.  x)   y");
+    let path = program.to_str().unwrap();
+    assert_eq!(chi(&["fmt", path, "--check"]).status.code(), Some(1));
+    assert!(chi(&["fmt", path]).status.success());
+    assert_eq!(fs::read_to_string(&program).unwrap(), "(λx.x) y\n");
+    assert!(chi(&["fmt", path, "--check"]).status.success());
+
+    let output = chi(&["convert", path]);
+    let abstract_syntax = stdout(&output).to_string();
+    let program = file("fmt", "id.abs", &abstract_syntax);
+    let output = chi(&["convert", program.to_str().unwrap()]);
+    assert_eq!(stdout(&output), "(λx.x) y");
+}
+
+#[test]
+fn test_diff() {
+    let left = file("diff", "left.chi", "λx. C(x, D())");
+    let right = file("diff", "right.chi", "λy. C(y, E())");
+    let (left, right) = (left.to_str().unwrap(), right.to_str().unwrap());
+    let output = chi(&["diff", left, right]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        stdout(&output),
+        "at the root:\n- λx.C(x, D())\n+ λy.C(y, E())"
+    );
+    let output = chi(&["diff", left, right, "--alpha", "--side-by-side"]);
+    assert_eq!(stdout(&output), "at LambdaBody.Argument(1):\nD() | E()");
+    assert!(chi(&["diff", left, left]).status.success());
+}
+
+#[test]
+fn test_bootstrapping() {
+    let program = file("bootstrapping", "suc.chi", "λx.Suc(x)");
+    let context = program.with_file_name("context.json");
+    let output = chi(&[
+        "decompile",
+        program.to_str().unwrap(),
+        "--context",
+        context.to_str().unwrap(),
+    ]);
+    assert_eq!(
+        stdout(&output),
+        "Lambda(Zero(), Const(Zero(), Cons(Var(Zero()), Nil())))"
+    );
+    assert!(context.exists());
+
+    let program = file("bootstrapping", "apply.chi", "(λx.Suc(x)) Zero()");
+    let output = chi(&[
+        "self-eval",
+        program.to_str().unwrap(),
+        "--context",
+        context.to_str().unwrap(),
+    ]);
+    assert_eq!(
+        stdout(&output),
+        "Const(Zero(), Cons(Const(Suc(Zero()), Nil()), Nil()))"
+    );
+    let output = chi(&["self-eval", program.to_str().unwrap(), "--limit", "10"]);
+    assert_eq!(output.status.code(), Some(1));
+
+    let program = file("bootstrapping", "var.chi", "x");
+    let output = chi(&["self-subst", program.to_str().unwrap(), "x", "Zero()"]);
+    assert_eq!(stdout(&output), "Const(Zero(), Nil())");
+}
@@ -0,0 +1,195 @@
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    process::{Child, ChildStdin, ChildStdout, Command, Stdio},
+};
+
+use serde_json::{json, Value};
+
+const URI: &str = "file:///add.chi";
+
+/// Talks to `chi-lsp` the way an editor would.
+struct Client {
+    server: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    next_id: u64,
+}
+
+impl Client {
+    fn start() -> Self {
+        let mut server = Command::new(env!("CARGO_BIN_EXE_chi-lsp"))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let stdin = server.stdin.take().unwrap();
+        let stdout = BufReader::new(server.stdout.take().unwrap());
+        let mut client = Self {
+            server,
+            stdin,
+            stdout,
+            next_id: 0,
+        };
+        client.request("initialize", json!({ "capabilities": {} }));
+        client.notify("initialized", json!({}));
+        client
+    }
+
+    fn send(&mut self, message: Value) {
+        let body = message.to_string();
+        write!(self.stdin, "Content-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+        self.stdin.flush().unwrap();
+    }
+
+    fn receive(&mut self) -> Value {
+        let mut length = 0;
+        loop {
+            let mut line = String::new();
+            self.stdout.read_line(&mut line).unwrap();
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            if let Some(value) = line.strip_prefix("Content-Length: ") {
+                length = value.parse().unwrap();
+            }
+        }
+        let mut body = vec![0; length];
+        self.stdout.read_exact(&mut body).unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    fn notify(&mut self, method: &str, params: Value) {
+        self.send(json!({ "jsonrpc": "2.0", "method": method, "params": params }));
+    }
+
+    /// Sends a request and returns the response, skipping notifications.
+    fn call(&mut self, method: &str, params: Value) -> Value {
+        self.next_id += 1;
+        let id = self.next_id;
+        self.send(json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }));
+        loop {
+            let message = self.receive();
+            if message["id"] == id {
+                return message;
+            }
+        }
+    }
+
+    fn request(&mut self, method: &str, params: Value) -> Value {
+        self.call(method, params)["result"].clone()
+    }
+
+    /// Changes the document and returns the diagnostics published for it.
+    fn change(&mut self, text: &str) -> Value {
+        self.notify(
+            "textDocument/didChange",
+            json!({
+                "textDocument": { "uri": URI, "version": 2 },
+                "contentChanges": [{ "text": text }],
+            }),
+        );
+        self.receive()["params"]["diagnostics"].clone()
+    }
+
+    fn at(&mut self, method: &str, line: u32, character: u32) -> Value {
+        self.request(
+            method,
+            json!({
+                "textDocument": { "uri": URI },
+                "position": { "line": line, "character": character },
+            }),
+        )
+    }
+
+    fn stop(mut self) {
+        self.request("shutdown", Value::Null);
+        self.notify("exit", Value::Null);
+        assert!(self.server.wait().unwrap().success());
+    }
+}
+
+fn range(start: (u32, u32), end: (u32, u32)) -> Value {
+    json!({
+        "start": { "line": start.0, "character": start.1 },
+        "end": { "line": end.0, "character": end.1 },
+    })
+}
+
+#[test]
+fn test_lsp() {
+    let mut client = Client::start();
+    client.notify(
+        "textDocument/didOpen",
+        json!({
+            "textDocument": { "uri": URI, "languageId": "chi", "version": 1, "text": "C(x," },
+        }),
+    );
+    let diagnostics = client.receive()["params"]["diagnostics"].clone();
+    assert_eq!(diagnostics.as_array().unwrap().len(), 1);
+    assert_eq!(diagnostics[0]["range"], range((0, 4), (0, 4)));
+
+    let code = "rec add = λm. λn. case n of {\n  Zero() → m;\n  Suc(k) → Suc(add m k)\n}";
+    assert_eq!(client.change(code), json!([]));
+
+    let hover = client.at("textDocument/hover", 1, 11);
+    assert_eq!(hover["range"], range((1, 11), (1, 12)));
+    let contents = hover["contents"]["value"].as_str().unwrap();
+    assert!(contents.contains("var m"));
+    assert!(contents.contains("Var(Zero())"));
+
+    let binder = |client: &mut Client, line, character| {
+        client.at("textDocument/definition", line, character)["range"].clone()
+    };
+    assert_eq!(binder(&mut client, 2, 21), range((2, 6), (2, 7)));
+    assert_eq!(binder(&mut client, 2, 19), range((0, 11), (0, 12)));
+    assert_eq!(binder(&mut client, 2, 15), range((0, 4), (0, 7)));
+
+    let symbols = client.request(
+        "textDocument/documentSymbol",
+        json!({ "textDocument": { "uri": URI } }),
+    );
+    assert_eq!(symbols.as_array().unwrap().len(), 1);
+    assert_eq!(symbols[0]["name"], "add");
+    assert_eq!(symbols[0]["selectionRange"], range((0, 4), (0, 7)));
+
+    let rename = |client: &mut Client, new_name| {
+        client.call(
+            "textDocument/rename",
+            json!({
+                "textDocument": { "uri": URI },
+                "position": { "line": 2, "character": 19 },
+                "newName": new_name,
+            }),
+        )
+    };
+    let edits = rename(&mut client, "a")["result"]["changes"][URI].clone();
+    let ranges: Vec<_> = edits
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|edit| edit["range"].clone())
+        .collect();
+    assert_eq!(
+        ranges,
+        vec![
+            range((0, 11), (0, 12)),
+            range((1, 11), (1, 12)),
+            range((2, 19), (2, 20))
+        ]
+    );
+    assert!(rename(&mut client, "n")["error"].is_object());
+
+    let formatting = json!({
+        "textDocument": { "uri": URI },
+        "options": { "tabSize": 4, "insertSpaces": true },
+    });
+    client.change("(λx.  x)   y");
+    let edits = client.request("textDocument/formatting", formatting.clone());
+    assert_eq!(edits[0]["newText"], "(λx.x) y\n");
+    client.change("(λx.x) y\n");
+    let edits = client.request("textDocument/formatting", formatting);
+    assert_eq!(edits, json!([]));
+
+    client.stop();
+}